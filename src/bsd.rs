@@ -0,0 +1,223 @@
+use crate::colours::{ColourEntry, Effect, Effects, LsColours, TerminalColour};
+
+/// The file types `LSCOLORS` sets, in the order BSD ls reads them, with their `LS_COLORS` keys and
+/// the names the TUI uses
+const FILE_TYPES: [(&str, &str); 11] = [
+    ("di", "Directory"),
+    ("ln", "Symlink"),
+    ("so", "Socket"),
+    ("pi", "Pipe"),
+    ("ex", "Executable"),
+    ("bd", "Block"),
+    ("cd", "Character"),
+    ("su", "SetUID"),
+    ("sg", "SetGID"),
+    ("tw", "Sticky Other Writable"),
+    ("ow", "Other Writable"),
+];
+
+/// Letters for the eight normal ANSI colours. Upper case letters make the foreground bold
+const LETTERS: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+
+/// What BSD ls uses when `LSCOLORS` is unset or too short
+const DEFAULT: &str = "exfxcxdxbxegedabagacad";
+
+/// Converts a scheme to an `LSCOLORS` string, along with a warning for every colour and effect
+/// that had to be changed or left out. Other file types and patterns have no place in `LSCOLORS`
+pub fn export(colours: &LsColours) -> (String, Vec<String>) {
+    let mut colours = colours.with_slots_resolved();
+    let builtins = colours.builtins_mut();
+    let mut value = String::new();
+    let mut warnings = vec![];
+
+    for (key, name) in FILE_TYPES {
+        let entry = &builtins.iter().find(|x| x.0 == key).unwrap().1;
+        let mut warn = |x: String| warnings.push(format!("{}: {}", name, x));
+
+        let bold = entry.effects.contains(Effect::Bold);
+        let (fg, bright) = letter(entry.fg, &mut warn);
+        value.push(match (fg, bold || bright) {
+            ('x', true) => {
+                warn("Bold needs a foreground colour, so it is left out".to_string());
+                'x'
+            }
+            (fg, true) => fg.to_ascii_uppercase(),
+            (fg, false) => fg,
+        });
+        if bright && !bold {
+            warn("Bright foregrounds can only be shown as bold".to_string());
+        }
+
+        let (bg, bright) = letter(entry.bg, &mut warn);
+        value.push(bg);
+        if bright {
+            warn("Bright backgrounds are shown as normal ones".to_string());
+        }
+
+        for effect in Effect::ORDER {
+            if effect != Effect::Bold && entry.effects.contains(effect) {
+                warn(format!("{} has no equivalent, so it is left out", effect));
+            }
+        }
+    }
+
+    (value, warnings)
+}
+
+/// Reads an `LSCOLORS` string, or a shell line setting one. File types it doesn't set are left
+/// uncoloured, as they are by BSD ls
+pub fn parse(contents: &str) -> Result<LsColours, String> {
+    let value = match contents.split_once("LSCOLORS=") {
+        Some((_, value)) => value,
+        None => contents,
+    };
+    let value = value
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_matches(['\'', '"', ';']);
+
+    if value.len() > DEFAULT.len() || value.len() % 2 != 0 || !value.is_ascii() {
+        return Err(format!(
+            "\'{}\' is not an LSCOLORS string of up to 11 pairs of letters",
+            value
+        ));
+    }
+
+    // BSD ls keeps its defaults for the file types a short string doesn't reach
+    let value = format!("{}{}", value, &DEFAULT[value.len()..]);
+    let chars = value.chars().collect::<Vec<_>>();
+
+    let mut colours = LsColours::default();
+    for (key, entry) in colours.builtins_mut() {
+        *entry = match FILE_TYPES.iter().position(|x| x.0 == key) {
+            Some(i) => {
+                let name = FILE_TYPES[i].1;
+                let (fg, bold) = colour(chars[i * 2]).ok_or(format!(
+                    "Invalid foreground \'{}\' for {}",
+                    chars[i * 2],
+                    name
+                ))?;
+                let (bg, _) = colour(chars[i * 2 + 1]).ok_or(format!(
+                    "Invalid background \'{}\' for {}",
+                    chars[i * 2 + 1],
+                    name
+                ))?;
+                let effects = match bold {
+                    true => Effect::Bold.into(),
+                    false => Effects::NONE,
+                };
+                ColourEntry::new(effects, fg, bg)
+            }
+            None => ColourEntry::default(),
+        };
+    }
+
+    Ok(colours)
+}
+
+/// The letter for a colour, and whether it is one of the bright colours. Colours outside the 16
+/// ANSI ones are replaced with the nearest of them
+fn letter(colour: Option<TerminalColour>, warn: &mut impl FnMut(String)) -> (char, bool) {
+    let colour = match colour {
+        Some(colour) => colour,
+        None => return ('x', false),
+    };
+
+    let n = match colour.palette_index() {
+        Some(n @ 0..=15) => n,
+        _ => {
+            let nearest = colour.nearest_named();
+            warn(format!(
+                "{} has no equivalent, so {} is used",
+                colour, nearest
+            ));
+            nearest.palette_index().unwrap()
+        }
+    };
+
+    (LETTERS[n as usize % 8], n >= 8)
+}
+
+/// The colour a letter stands for, and whether it asks for bold
+fn colour(letter: char) -> Option<(Option<TerminalColour>, bool)> {
+    match letter {
+        'x' => Some((None, false)),
+        'X' => Some((None, true)),
+        c => {
+            let n = LETTERS.iter().position(|x| *x == c.to_ascii_lowercase())?;
+            Some((Some(TerminalColour::ANSI[n]), c.is_ascii_uppercase()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_default() {
+        let colours = parse(DEFAULT).unwrap();
+        let (value, warnings) = export(&colours);
+        assert_eq!(value, DEFAULT);
+        assert_eq!(value.len(), 22);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        for value in ["GxFxCxDxBxegedabagaced", "gxHbexcxdxfgagacadbhcf"] {
+            let (exported, warnings) = export(&parse(value).unwrap());
+            assert_eq!(exported, value);
+            assert!(warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn parse_short_and_shell_lines() {
+        let short = parse("Gx").unwrap();
+        assert_eq!(
+            short.dir,
+            ColourEntry::new(Effect::Bold.into(), Some(TerminalColour::Cyan), None)
+        );
+        // The rest come from BSD ls's defaults
+        assert_eq!(export(&short).0, format!("Gx{}", &DEFAULT[2..]));
+        assert_eq!(
+            parse("export LSCOLORS='GxFxCxDxBxegedabagaced'").unwrap(),
+            parse("GxFxCxDxBxegedabagaced").unwrap()
+        );
+        // Kinds of file LSCOLORS doesn't cover are left uncoloured
+        assert_eq!(short.file, ColourEntry::default());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("exf").is_err());
+        assert!(parse(&"ex".repeat(12)).is_err());
+        assert!(parse("ix").is_err());
+    }
+
+    #[test]
+    fn warnings() {
+        let mut colours = parse(DEFAULT).unwrap();
+        colours.dir.fg = Some(TerminalColour::Indexed(208));
+        colours.link.fg = Some(TerminalColour::Rgb(0, 0, 250));
+        colours.sock.fg = Some(TerminalColour::LightRed);
+        colours.sock.effects = Effects::NONE;
+        colours.pipe.effects.insert(Effect::Underline);
+        colours.exec = ColourEntry::new(Effect::Bold.into(), None, None);
+
+        let (value, warnings) = export(&colours);
+        assert_eq!(value, "dxexBxdxxxegedabagacad");
+        assert_eq!(
+            warnings,
+            [
+                "Directory: Index 208 has no equivalent, so Orange is used",
+                "Symlink: #0000fa has no equivalent, so Blue is used",
+                "Socket: Bright foregrounds can only be shown as bold",
+                "Pipe: Underline has no equivalent, so it is left out",
+                "Executable: Bold needs a foreground colour, so it is left out",
+            ]
+        );
+    }
+}
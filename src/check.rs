@@ -0,0 +1,215 @@
+use crate::colours::LsColours;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The scheme can't be exported as intended
+    Error,
+    /// The scheme exports, but some rules won't do what they look like they do
+    Warning,
+}
+
+/// A problem with the patterns of a scheme
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Problem {
+    pub severity: Severity,
+    /// The pattern at fault, in the form it is stored
+    pub pattern: String,
+    pub message: String,
+}
+
+/// Checks that a pattern can be written to `LS_COLORS`, a dircolors database and a shell command.
+/// Characters ls reads specially are escaped rather than rejected
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.is_empty() {
+        Err("Pattern cannot be empty".to_string())
+    } else if pattern.chars().any(|c| c == '\'' || c == '"') {
+        Err("Pattern cannot contain quotes".to_string())
+    } else if pattern.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        Err("Pattern cannot contain spaces or control characters".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Finds invalid, duplicated and shadowed patterns. Rules are compared in the order ls reads them,
+/// where a later rule wins over an earlier one that matches the same file. ls also ignores case
+/// unless the variants have different colours, so case variants are reported too
+pub fn check(colours: &LsColours) -> Vec<Problem> {
+    let mut rules = vec![];
+    for group in &colours.groups {
+        for pattern in &group.patterns {
+            rules.push((format!("group {}", group.name), pattern, group.entry));
+        }
+    }
+    for (pattern, entry) in &colours.extensions {
+        rules.push(("extensions".to_string(), pattern, *entry));
+    }
+
+    let mut problems = vec![];
+
+    for (i, (location, pattern, entry)) in rules.iter().enumerate() {
+        if let Err(e) = validate_pattern(pattern) {
+            problems.push(Problem {
+                severity: Severity::Error,
+                pattern: pattern.to_string(),
+                message: format!("*{} in {}: {}", pattern, location, e),
+            });
+        }
+
+        for (later_location, later, later_entry) in &rules[i + 1..] {
+            let message = if pattern == later && location == later_location {
+                problems.push(Problem {
+                    severity: Severity::Error,
+                    pattern: pattern.to_string(),
+                    message: format!("*{} appears more than once in {}", pattern, location),
+                });
+                continue;
+            } else if pattern == later {
+                format!(
+                    "*{} in {} is overridden by the same pattern in {}",
+                    pattern, location, later_location
+                )
+            } else if pattern.eq_ignore_ascii_case(later) {
+                match entry == later_entry {
+                    true => format!(
+                        "*{} in {} and *{} in {} differ only in case, and ls already ignores case",
+                        pattern, location, later, later_location
+                    ),
+                    false => format!(
+                        "*{} in {} and *{} in {} differ only in case, so ls stops ignoring case for them",
+                        pattern, location, later, later_location
+                    ),
+                }
+            } else if pattern
+                .to_ascii_lowercase()
+                .ends_with(&later.to_ascii_lowercase())
+            {
+                format!(
+                    "*{} in {} never applies, as *{} in {} comes later and matches the same files",
+                    pattern, location, later, later_location
+                )
+            } else {
+                continue;
+            };
+
+            problems.push(Problem {
+                severity: Severity::Warning,
+                pattern: pattern.to_string(),
+                message,
+            });
+        }
+    }
+
+    problems
+}
+
+/// Problems that adding a pattern to the named group, or to `extensions`, would introduce
+pub fn new_problems(colours: &LsColours, group: Option<&str>, pattern: &str) -> Vec<Problem> {
+    let before = check(colours);
+    let mut after = colours.clone();
+    after.add_pattern(group, pattern.to_string());
+
+    check(&after)
+        .into_iter()
+        .filter(|x| !before.iter().any(|y| y.message == x.message))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colours::{ColourEntry, Effects, ExtensionGroup, TerminalColour};
+
+    fn scheme(group: &[&str], extensions: &[&str]) -> LsColours {
+        let red = ColourEntry::new(Effects::NONE, Some(TerminalColour::Red), None);
+        let mut colours = LsColours::uncoloured();
+        colours.groups = vec![ExtensionGroup {
+            name: "Archives".to_string(),
+            entry: red,
+            patterns: group.iter().map(|x| x.to_string()).collect(),
+            inherit_entry: false,
+        }];
+        colours.extensions = extensions.iter().map(|x| (x.to_string(), red)).collect();
+        colours
+    }
+
+    fn messages(problems: Vec<Problem>) -> Vec<String> {
+        problems.into_iter().map(|x| x.message).collect()
+    }
+
+    #[test]
+    fn duplicates() {
+        let problems = check(&scheme(&[".tar", ".zip", ".tar"], &[".zip"]));
+        assert_eq!(
+            messages(problems.clone()),
+            vec![
+                "*.tar appears more than once in group Archives",
+                "*.zip in group Archives is overridden by the same pattern in extensions",
+            ]
+        );
+        assert_eq!(problems[0].severity, Severity::Error);
+        assert_eq!(problems[1].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn case_variants() {
+        let mut colours = scheme(&[".tar"], &[".TAR"]);
+        assert_eq!(
+            messages(check(&colours)),
+            vec![
+                "*.tar in group Archives and *.TAR in extensions differ only in case, and ls \
+                 already ignores case"
+            ]
+        );
+
+        colours.extensions[0].1 = ColourEntry::default();
+        assert!(check(&colours)[0]
+            .message
+            .ends_with("so ls stops ignoring case for them"));
+    }
+
+    #[test]
+    fn shadowed_patterns() {
+        assert_eq!(
+            messages(check(&scheme(&[".tar.gz"], &["gz"]))),
+            vec![
+                "*.tar.gz in group Archives never applies, as *gz in extensions comes later and \
+                 matches the same files"
+            ]
+        );
+        // The more specific pattern coming later is how it's meant to be done
+        assert!(check(&scheme(&["gz"], &[".tar.gz", ".tgz"])).is_empty());
+    }
+
+    #[test]
+    fn adding_patterns() {
+        let colours = scheme(&[".tar"], &[]);
+        assert!(new_problems(&colours, None, ".zst").is_empty());
+        assert_eq!(
+            messages(new_problems(&colours, None, ".TAR")),
+            vec![
+                "*.tar in group Archives and *.TAR in extensions differ only in case, so ls stops \
+                 ignoring case for them"
+            ]
+        );
+        assert_eq!(
+            new_problems(&colours, Some("Archives"), "a b")[0].severity,
+            Severity::Error
+        );
+
+        // Problems the scheme already has aren't reported again
+        let colours = scheme(&[".tar", ".tar"], &[]);
+        assert!(new_problems(&colours, Some("Archives"), ".zip").is_empty());
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!(validate_pattern("").is_err());
+        assert!(validate_pattern("it's").is_err());
+        assert!(validate_pattern("a b").is_err());
+        assert!(validate_pattern("a\tb").is_err());
+        // ls reads these specially, but they're escaped on export
+        assert_eq!(validate_pattern("*a\\b:c=d^"), Ok(()));
+    }
+}
@@ -1,4 +1,7 @@
-use ratatui::style::Color;
+use ratatui::style::{Color, Modifier};
+
+/// The lowest contrast ratio WCAG AA accepts for normal text
+pub const MIN_CONTRAST: f64 = 4.5;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
 pub struct LsColours {
@@ -20,34 +23,137 @@ pub struct LsColours {
     pub sticky: ColourEntry,
     pub exec: ColourEntry,
     pub missing: ColourEntry,
+    #[serde(default)]
+    pub multi_hardlink: ColourEntry,
+    #[serde(default)]
+    pub capability: ColourEntry,
+    /// Whether symlinks take the colour of the file they point to, as `ln=target` asks, in place
+    /// of `link`
+    #[serde(default)]
+    pub link_target: bool,
+    /// Raw escape sequences ls writes around colour codes, left unset to use the ls defaults
+    #[serde(default)]
+    pub left_code: Option<String>,
+    #[serde(default)]
+    pub right_code: Option<String>,
+    #[serde(default)]
+    pub end_code: Option<String>,
+    #[serde(default)]
+    pub clear_line: Option<String>,
+    /// Rules matched against the end of file names, stored without the leading `*` that ls
+    /// expects. See `PatternKind` for how they are told apart
     pub extensions: Vec<(String, ColourEntry)>,
+    /// Named sets of patterns sharing one colour. Rules in `extensions` take precedence
+    #[serde(default)]
+    pub groups: Vec<ExtensionGroup>,
+    /// Background of the terminal the scheme is meant for. Only used to check contrast
+    #[serde(default)]
+    pub background: TerminalColour,
+    /// Colours of the terminal the scheme is meant for, used in place of xterm's defaults
+    #[serde(default)]
+    pub palette: Option<TerminalPalette>,
+    /// Named colours that entries can refer to with `TerminalColour::Slot`, so one edit changes
+    /// every entry using it
+    #[serde(default)]
+    pub slots: Vec<(String, TerminalColour)>,
+    /// Name of the scheme this one builds on. Anything it doesn't set itself comes from the
+    /// parent when exporting
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// `LS_COLORS` keys of the entries set by this scheme rather than its parent, along with
+    /// `background` if it sets that
+    #[serde(default)]
+    pub overrides: Vec<String>,
+    /// Whether the scheme is its parent's variant for the opposite terminal background, so the
+    /// parent's colours are remapped before they are inherited
+    #[serde(default)]
+    pub variant: bool,
+    /// Colours for the parts of an eza listing that ls doesn't have, by `EZA_COLORS` key. `None`
+    /// unless the scheme is also used with eza
+    #[serde(default)]
+    pub eza: Option<Vec<(String, ColourEntry)>>,
+    /// Whether the eza section is turned off. Its colours are kept, so turning it back on restores
+    /// them
+    #[serde(default)]
+    pub eza_disabled: bool,
+}
+
+/// The 16 ANSI colours of a terminal theme, as loaded from a palette file
+#[derive(Debug, Clone, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
+pub struct TerminalPalette {
+    /// File the palette was loaded from
+    pub source: std::path::PathBuf,
+    /// Truecolor values in the order of `TerminalColour::ANSI`
+    pub ansi: [TerminalColour; 16],
+    pub foreground: Option<TerminalColour>,
+    pub background: Option<TerminalColour>,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
+pub struct ExtensionGroup {
+    pub name: String,
+    pub entry: ColourEntry,
+    /// Patterns in the same form as `LsColours::extensions`
+    pub patterns: Vec<String>,
+    /// Whether the group only adds patterns to the parent's group of the same name, leaving its
+    /// colour to come from the parent
+    #[serde(default)]
+    pub inherit_entry: bool,
 }
 
 impl LsColours {
+    /// A scheme that leaves every kind of file uncoloured, for importers to fill in with only what
+    /// they read
+    pub fn uncoloured() -> Self {
+        let mut colours = LsColours::default();
+        for (_, entry) in colours.builtins_mut() {
+            *entry = ColourEntry::default();
+        }
+        colours
+    }
+
     pub fn parse(&self) -> String {
         let mut out = String::new();
+        let colours = self.with_slots_resolved();
 
-        out.push_str(&format!("no={}:", self.norm.parse()));
-        out.push_str(&format!("rs={}:", self.rs.parse()));
-        out.push_str(&format!("fi={}:", self.file.parse()));
-        out.push_str(&format!("di={}:", self.dir.parse()));
-        out.push_str(&format!("ln={}:", self.link.parse()));
-        out.push_str(&format!("pi={}:", self.pipe.parse()));
-        out.push_str(&format!("do={}:", self.door.parse()));
-        out.push_str(&format!("bd={}:", self.block.parse()));
-        out.push_str(&format!("cd={}:", self.char.parse()));
-        out.push_str(&format!("or={}:", self.orphan.parse()));
-        out.push_str(&format!("so={}:", self.sock.parse()));
-        out.push_str(&format!("su={}:", self.setuid.parse()));
-        out.push_str(&format!("sg={}:", self.setgid.parse()));
-        out.push_str(&format!("tw={}:", self.sticky_other_writable.parse()));
-        out.push_str(&format!("ow={}:", self.other_writable.parse()));
-        out.push_str(&format!("st={}:", self.sticky.parse()));
-        out.push_str(&format!("ex={}:", self.exec.parse()));
-        out.push_str(&format!("mi={}:", self.missing.parse()));
-
-        for ext in &self.extensions {
-            out.push_str(&format!("*{}={}:", ext.0, ext.1.parse()));
+        out.push_str(&format!("no={}:", colours.norm.parse()));
+        out.push_str(&format!("rs={}:", colours.rs.parse()));
+        out.push_str(&format!("fi={}:", colours.file.parse()));
+        out.push_str(&format!("di={}:", colours.dir.parse()));
+        match colours.link_target {
+            true => out.push_str("ln=target:"),
+            false => out.push_str(&format!("ln={}:", colours.link.parse())),
+        }
+        out.push_str(&format!("pi={}:", colours.pipe.parse()));
+        out.push_str(&format!("do={}:", colours.door.parse()));
+        out.push_str(&format!("bd={}:", colours.block.parse()));
+        out.push_str(&format!("cd={}:", colours.char.parse()));
+        out.push_str(&format!("or={}:", colours.orphan.parse()));
+        out.push_str(&format!("so={}:", colours.sock.parse()));
+        out.push_str(&format!("su={}:", colours.setuid.parse()));
+        out.push_str(&format!("sg={}:", colours.setgid.parse()));
+        out.push_str(&format!("tw={}:", colours.sticky_other_writable.parse()));
+        out.push_str(&format!("ow={}:", colours.other_writable.parse()));
+        out.push_str(&format!("st={}:", colours.sticky.parse()));
+        out.push_str(&format!("ex={}:", colours.exec.parse()));
+        out.push_str(&format!("mi={}:", colours.missing.parse()));
+        out.push_str(&format!("mh={}:", colours.multi_hardlink.parse()));
+        out.push_str(&format!("ca={}:", colours.capability.parse()));
+
+        let escapes = [
+            ("lc", &colours.left_code),
+            ("rc", &colours.right_code),
+            ("ec", &colours.end_code),
+            ("cl", &colours.clear_line),
+        ];
+        for (key, code) in escapes {
+            if let Some(code) = code {
+                out.push_str(&format!("{}={}:", key, code));
+            }
+        }
+
+        for ext in colours.patterns() {
+            out.push_str(&format!("*{}={}:", escape_pattern(&ext.0), ext.1.parse()));
         }
 
         // Remove trailing ':'
@@ -55,43 +161,584 @@ impl LsColours {
 
         out
     }
+
+    /// Every pattern rule with groups expanded, in the order ls reads them. ls lets later rules
+    /// override earlier ones, so groups come first
+    pub fn patterns(&self) -> Vec<(String, ColourEntry)> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.patterns.iter().map(|x| (x.clone(), group.entry)))
+            .chain(self.extensions.iter().cloned())
+            .collect()
+    }
+
+    /// WCAG contrast ratio of an entry's text against its background, or the terminal background
+    /// if it has none. Entries without a foreground use the normal colour, then the palette's
+    /// foreground, and failing that whichever of black and white stands out more, as most
+    /// terminals' default text does
+    pub fn contrast(&self, entry: &ColourEntry) -> f64 {
+        let (fg, bg) = self.resolve(entry);
+        fg.contrast(&bg)
+    }
+
+    /// The foreground and background an entry is drawn with on the scheme's terminal, filling in
+    /// unset colours the same way as `contrast`. Colours come from the scheme's palette if it has
+    /// one
+    pub fn resolve(&self, entry: &ColourEntry) -> (TerminalColour, TerminalColour) {
+        let bg = self.rgb(entry.bg.unwrap_or(self.background));
+        let fg = entry
+            .fg
+            .or(self.norm.fg)
+            .or(self.palette.as_ref().and_then(|x| x.foreground))
+            .unwrap_or_else(|| {
+                match bg.contrast(&TerminalColour::White) > bg.contrast(&TerminalColour::Black) {
+                    true => TerminalColour::White,
+                    false => TerminalColour::Black,
+                }
+            });
+        (self.rgb(fg), bg)
+    }
+
+    /// Looks a colour up in the scheme's slots and then its palette, leaving it as it is if it
+    /// isn't one of the 16 ANSI colours or there's no palette
+    pub fn rgb(&self, colour: TerminalColour) -> TerminalColour {
+        let colour = self.slot_colour(colour);
+        match (&self.palette, colour.palette_index()) {
+            (Some(palette), Some(n @ 0..=15)) => palette.ansi[n as usize],
+            _ => colour,
+        }
+    }
+
+    /// The colour a slot reference stands for, or the colour itself if it isn't one. References to
+    /// missing slots become black
+    pub fn slot_colour(&self, colour: TerminalColour) -> TerminalColour {
+        match colour {
+            TerminalColour::Slot(n) => self.slots.get(n as usize).map(|x| x.1).unwrap_or_default(),
+            c => c,
+        }
+    }
+
+    /// The entries for kinds of file, with their `LS_COLORS` keys
+    pub fn builtins_mut(&mut self) -> [(&'static str, &mut ColourEntry); 20] {
+        [
+            ("no", &mut self.norm),
+            ("rs", &mut self.rs),
+            ("fi", &mut self.file),
+            ("di", &mut self.dir),
+            ("ln", &mut self.link),
+            ("pi", &mut self.pipe),
+            ("do", &mut self.door),
+            ("bd", &mut self.block),
+            ("cd", &mut self.char),
+            ("or", &mut self.orphan),
+            ("so", &mut self.sock),
+            ("su", &mut self.setuid),
+            ("sg", &mut self.setgid),
+            ("tw", &mut self.sticky_other_writable),
+            ("ow", &mut self.other_writable),
+            ("st", &mut self.sticky),
+            ("ex", &mut self.exec),
+            ("mi", &mut self.missing),
+            ("mh", &mut self.multi_hardlink),
+            ("ca", &mut self.capability),
+        ]
+    }
+
+    /// The eza section, unless it is turned off
+    pub fn eza_section(&self) -> Option<&Vec<(String, ColourEntry)>> {
+        match self.eza_disabled {
+            true => None,
+            false => self.eza.as_ref(),
+        }
+    }
+
+    /// The raw escape sequences, with their `LS_COLORS` keys
+    pub fn escapes_mut(&mut self) -> [(&'static str, &mut Option<String>); 4] {
+        [
+            ("lc", &mut self.left_code),
+            ("rc", &mut self.right_code),
+            ("ec", &mut self.end_code),
+            ("cl", &mut self.clear_line),
+        ]
+    }
+
+    /// Every colour entry of the scheme, including groups, extensions and the eza section
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = &mut ColourEntry> {
+        [
+            &mut self.norm,
+            &mut self.rs,
+            &mut self.file,
+            &mut self.dir,
+            &mut self.link,
+            &mut self.pipe,
+            &mut self.door,
+            &mut self.block,
+            &mut self.char,
+            &mut self.orphan,
+            &mut self.sock,
+            &mut self.setuid,
+            &mut self.setgid,
+            &mut self.sticky_other_writable,
+            &mut self.other_writable,
+            &mut self.sticky,
+            &mut self.exec,
+            &mut self.missing,
+            &mut self.multi_hardlink,
+            &mut self.capability,
+        ]
+        .into_iter()
+        .chain(self.groups.iter_mut().map(|x| &mut x.entry))
+        .chain(self.extensions.iter_mut().map(|x| &mut x.1))
+        .chain(self.eza.iter_mut().flatten().map(|x| &mut x.1))
+    }
+
+    /// Records that the scheme sets an entry, or `background`, itself. Does nothing without a
+    /// parent
+    pub fn set_override(&mut self, key: &str) {
+        if self.parent.is_some() && !self.overrides.iter().any(|x| x == key) {
+            self.overrides.push(key.to_string());
+        }
+    }
+
+    /// Makes the scheme build on another, keeping as overrides only the entries and extensions
+    /// that differ from what it would inherit
+    pub fn set_parent(&mut self, name: &str, parent: &LsColours) {
+        let mut own = self.with_slots_resolved();
+        let mut inherited = parent.with_slots_resolved();
+
+        self.parent = Some(name.to_string());
+        self.variant = false;
+        self.overrides = own
+            .builtins_mut()
+            .into_iter()
+            .zip(inherited.builtins_mut())
+            .filter(|(a, b)| a.1 != b.1)
+            .map(|x| x.0 .0.to_string())
+            .collect();
+        for ((key, code), (_, inherited)) in
+            own.escapes_mut().into_iter().zip(inherited.escapes_mut())
+        {
+            if code != inherited {
+                self.overrides.push(key.to_string());
+            }
+        }
+        if own.link_target != inherited.link_target && !self.overrides.iter().any(|x| x == "ln") {
+            self.overrides.push("ln".to_string());
+        }
+        if own.background != inherited.background {
+            self.overrides.push("background".to_string());
+        }
+        self.extensions = self
+            .extensions
+            .iter()
+            .zip(&own.extensions)
+            .filter(|x| !inherited.extensions.contains(x.1))
+            .map(|x| x.0.clone())
+            .collect();
+    }
+
+    /// Fills in everything the scheme leaves to its parent, which should already have its own
+    /// parents filled in. Entries not in `overrides` and unset escapes that aren't either come from
+    /// the parent, so an escape in `overrides` can be unset where the parent sets it. Groups
+    /// of the same name take this scheme's colour and both sets of patterns, and extensions are
+    /// added after the parent's, replacing any with the same pattern
+    pub fn inherit(&self, parent: &LsColours) -> LsColours {
+        // Slots belong to the scheme that defined them, so the parent's are resolved here
+        let mut parent = parent.with_slots_resolved();
+        let mut colours = self.clone();
+
+        for ((key, entry), (_, inherited)) in colours
+            .builtins_mut()
+            .into_iter()
+            .zip(parent.builtins_mut())
+        {
+            if !self.overrides.iter().any(|x| x == key) {
+                *entry = *inherited;
+            }
+        }
+
+        if !self.overrides.iter().any(|x| x == "ln") {
+            colours.link_target = parent.link_target;
+        }
+
+        for ((key, code), (_, inherited)) in
+            colours.escapes_mut().into_iter().zip(parent.escapes_mut())
+        {
+            if code.is_none() && !self.overrides.iter().any(|x| x == key) {
+                code.clone_from(inherited);
+            }
+        }
+
+        colours.groups = parent.groups.clone();
+        for group in &self.groups {
+            match colours.groups.iter_mut().find(|x| x.name == group.name) {
+                Some(inherited) => {
+                    if !group.inherit_entry {
+                        inherited.entry = group.entry;
+                    }
+                    for pattern in &group.patterns {
+                        if !inherited.patterns.contains(pattern) {
+                            inherited.patterns.push(pattern.clone());
+                        }
+                    }
+                }
+                None => colours.groups.push(group.clone()),
+            }
+        }
+
+        colours.extensions = parent
+            .extensions
+            .iter()
+            .filter(|x| !self.extensions.iter().any(|y| y.0 == x.0))
+            .chain(&self.extensions)
+            .cloned()
+            .collect();
+
+        // eza colours are layered like extensions, so a child can change a few of them
+        if let Some(inherited) = parent.eza_section() {
+            let own = self.eza.as_deref().unwrap_or_default();
+            colours.eza = Some(
+                inherited
+                    .iter()
+                    .filter(|x| !own.iter().any(|y| y.0 == x.0))
+                    .chain(own)
+                    .cloned()
+                    .collect(),
+            );
+        }
+
+        if !self.overrides.iter().any(|x| x == "background") {
+            colours.background = parent.background;
+        }
+        if colours.palette.is_none() {
+            colours.palette = parent.palette.clone();
+        }
+
+        colours
+    }
+
+    /// A copy of the scheme with every slot reference replaced by the slot's colour, as written
+    /// when exporting
+    pub fn with_slots_resolved(&self) -> LsColours {
+        let mut resolved = self.clone();
+        for entry in resolved.entries_mut() {
+            entry.fg = entry.fg.map(|x| self.slot_colour(x));
+            entry.bg = entry.bg.map(|x| self.slot_colour(x));
+        }
+        resolved.background = self.slot_colour(self.background);
+        resolved
+    }
+
+    /// Removes a slot, giving the entries that referred to it its colour
+    pub fn remove_slot(&mut self, slot: u8) {
+        if slot as usize >= self.slots.len() {
+            return;
+        }
+
+        let colour = self.slots.remove(slot as usize).1;
+        let update = |c: TerminalColour| match c {
+            TerminalColour::Slot(n) if n == slot => colour,
+            TerminalColour::Slot(n) if n > slot => TerminalColour::Slot(n - 1),
+            c => c,
+        };
+        for entry in self.entries_mut() {
+            entry.fg = entry.fg.map(update);
+            entry.bg = entry.bg.map(update);
+        }
+    }
+
+    /// Number of entries referring to a slot
+    pub fn slot_uses(&self, slot: u8) -> usize {
+        let mut colours = self.clone();
+        colours
+            .entries_mut()
+            .filter(|x| {
+                x.fg == Some(TerminalColour::Slot(slot)) || x.bg == Some(TerminalColour::Slot(slot))
+            })
+            .count()
+    }
+
+    /// An entry with its colours looked up in the scheme's palette, for previews
+    pub fn preview(&self, entry: ColourEntry) -> ColourEntry {
+        ColourEntry {
+            fg: entry.fg.map(|x| self.rgb(x)),
+            bg: entry.bg.map(|x| self.rgb(x)),
+            ..entry
+        }
+    }
+
+    /// Adds an uncoloured pattern to the end of the named group, or to `extensions` if there's no
+    /// such group
+    pub fn add_pattern(&mut self, group: Option<&str>, pattern: String) {
+        match self
+            .groups
+            .iter_mut()
+            .find(|x| Some(x.name.as_str()) == group)
+        {
+            Some(group) => group.patterns.push(pattern),
+            None => self
+                .extensions
+                .push((pattern, ColourEntry::new(Effects::NONE, None, None))),
+        }
+    }
+
+    /// Whether a pattern is already in the named group, or in `extensions` if there's no group
+    pub fn contains_pattern(&self, group: Option<&str>, pattern: &str) -> bool {
+        match group {
+            Some(name) => self
+                .groups
+                .iter()
+                .filter(|x| x.name == name)
+                .any(|x| x.patterns.iter().any(|x| x == pattern)),
+            None => self.extensions.iter().any(|x| x.0 == pattern),
+        }
+    }
 }
 
 impl Default for LsColours {
     fn default() -> Self {
         Self {
-            norm: ColourEntry::new(Effects::Default, Some(TerminalColour::White), None),
-            rs: ColourEntry::new(Effects::Default, Some(TerminalColour::White), None),
-            file: ColourEntry::new(Effects::Default, Some(TerminalColour::LightBlue), None),
-            dir: ColourEntry::new(Effects::Bold, Some(TerminalColour::Blue), None),
-            link: ColourEntry::new(Effects::Default, Some(TerminalColour::Gray), None),
-            pipe: ColourEntry::new(Effects::Default, Some(TerminalColour::LightPurple), None),
-            door: ColourEntry::new(Effects::Default, Some(TerminalColour::Purple), None),
-            block: ColourEntry::new(Effects::Default, Some(TerminalColour::Yellow), None),
-            char: ColourEntry::new(Effects::Bold, Some(TerminalColour::Yellow), None),
-            orphan: ColourEntry::new(Effects::Default, Some(TerminalColour::Red), None),
-            sock: ColourEntry::new(Effects::Bold, Some(TerminalColour::Orange), None),
-            setuid: ColourEntry::new(Effects::Default, Some(TerminalColour::Blue), None),
-            setgid: ColourEntry::new(Effects::Default, Some(TerminalColour::Blue), None),
+            norm: ColourEntry::new(Effects::NONE, Some(TerminalColour::White), None),
+            rs: ColourEntry::new(Effects::NONE, Some(TerminalColour::White), None),
+            file: ColourEntry::new(Effects::NONE, Some(TerminalColour::LightBlue), None),
+            dir: ColourEntry::new(Effect::Bold.into(), Some(TerminalColour::Blue), None),
+            link: ColourEntry::new(Effects::NONE, Some(TerminalColour::Gray), None),
+            pipe: ColourEntry::new(Effects::NONE, Some(TerminalColour::LightPurple), None),
+            door: ColourEntry::new(Effects::NONE, Some(TerminalColour::Purple), None),
+            block: ColourEntry::new(Effects::NONE, Some(TerminalColour::Yellow), None),
+            char: ColourEntry::new(Effect::Bold.into(), Some(TerminalColour::Yellow), None),
+            orphan: ColourEntry::new(Effects::NONE, Some(TerminalColour::Red), None),
+            sock: ColourEntry::new(Effect::Bold.into(), Some(TerminalColour::Orange), None),
+            setuid: ColourEntry::new(Effects::NONE, Some(TerminalColour::Blue), None),
+            setgid: ColourEntry::new(Effects::NONE, Some(TerminalColour::Blue), None),
             sticky_other_writable: ColourEntry::new(
-                Effects::Default,
+                Effects::NONE,
                 Some(TerminalColour::Blue),
                 None,
             ),
-            other_writable: ColourEntry::new(
-                Effects::Default,
-                Some(TerminalColour::LightBlue),
-                None,
-            ),
-            sticky: ColourEntry::new(Effects::Bold, Some(TerminalColour::LightBlue), None),
-            exec: ColourEntry::new(Effects::Default, Some(TerminalColour::Green), None),
-            missing: ColourEntry::new(Effects::Default, Some(TerminalColour::Red), None),
+            other_writable: ColourEntry::new(Effects::NONE, Some(TerminalColour::LightBlue), None),
+            sticky: ColourEntry::new(Effect::Bold.into(), Some(TerminalColour::LightBlue), None),
+            exec: ColourEntry::new(Effects::NONE, Some(TerminalColour::Green), None),
+            missing: ColourEntry::new(Effects::NONE, Some(TerminalColour::Red), None),
+            multi_hardlink: ColourEntry::default(),
+            capability: ColourEntry::default(),
+            link_target: false,
+            left_code: None,
+            right_code: None,
+            end_code: None,
+            clear_line: None,
             extensions: vec![],
+            groups: vec![],
+            background: TerminalColour::default(),
+            palette: None,
+            slots: vec![],
+            parent: None,
+            overrides: vec![],
+            variant: false,
+            eza: None,
+            eza_disabled: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
+impl std::str::FromStr for LsColours {
+    type Err = ParseError;
+
+    /// Parses an `LS_COLORS` string. Keys that are absent are left uncoloured
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut colours = LsColours::uncoloured();
+
+        let mut pairs = vec![];
+        let mut rest = s;
+        while let Some(i) = find_unescaped(rest, ':') {
+            pairs.push(&rest[..i]);
+            rest = &rest[i + 1..];
+        }
+        pairs.push(rest);
+
+        for pair in pairs.into_iter().filter(|x| !x.is_empty()) {
+            let (key, value) = find_unescaped(pair, '=')
+                .map(|i| (&pair[..i], &pair[i + 1..]))
+                .ok_or(ParseError::MalformedPair(pair.to_string()))?;
+
+            if key == "ln" && value == "target" {
+                colours.link_target = true;
+                continue;
+            }
+
+            // Escape sequences are kept as written rather than read as colours
+            if let Some((_, escape)) = colours.escapes_mut().into_iter().find(|x| x.0 == key) {
+                *escape = Some(value.to_string());
+                continue;
+            }
+
+            let entry = ColourEntry::from_sgr(value).ok_or(ParseError::InvalidSequence {
+                key: key.to_string(),
+                sequence: value.to_string(),
+            })?;
+
+            match key {
+                "no" => colours.norm = entry,
+                "rs" => colours.rs = entry,
+                "fi" => colours.file = entry,
+                "di" => colours.dir = entry,
+                "ln" => colours.link = entry,
+                "pi" => colours.pipe = entry,
+                "do" => colours.door = entry,
+                "bd" => colours.block = entry,
+                "cd" => colours.char = entry,
+                "or" => colours.orphan = entry,
+                "so" => colours.sock = entry,
+                "su" => colours.setuid = entry,
+                "sg" => colours.setgid = entry,
+                "tw" => colours.sticky_other_writable = entry,
+                "ow" => colours.other_writable = entry,
+                "st" => colours.sticky = entry,
+                "ex" => colours.exec = entry,
+                "mi" => colours.missing = entry,
+                "mh" => colours.multi_hardlink = entry,
+                "ca" => colours.capability = entry,
+                _ => match key.strip_prefix('*').map(unescape_pattern) {
+                    Some(ext) if !ext.is_empty() => {
+                        match colours.extensions.iter_mut().find(|x| x.0 == ext) {
+                            Some(x) => x.1 = entry,
+                            None => colours.extensions.push((ext, entry)),
+                        }
+                    }
+                    _ => return Err(ParseError::UnknownKey(key.to_string())),
+                },
+            }
+        }
+
+        Ok(colours)
+    }
+}
+
+/// Characters ls reads specially in `LS_COLORS` keys, so a pattern has to escape them with a
+/// backslash to match them literally
+const SPECIAL_CHARS: [char; 4] = ['\\', ':', '=', '^'];
+
+/// Escapes a pattern for use as an `LS_COLORS` key, as dircolors does
+pub fn escape_pattern(pattern: &str) -> String {
+    let mut out = String::new();
+    for c in pattern.chars() {
+        if SPECIAL_CHARS.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Reverses `escape_pattern`. Other backslash sequences are kept as written
+fn unescape_pattern(key: &str) -> String {
+    let mut out = String::new();
+    let mut chars = key.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '\\' && SPECIAL_CHARS.contains(next) => {
+                out.push(*next);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether an escape sequence can be written into `LS_COLORS` as it is. ls ends a value at the
+/// first `:` and our own parser splits keys at the first `=`, so both have to be escaped with a
+/// backslash
+pub fn valid_escape(code: &str) -> bool {
+    find_unescaped(code, ':').is_none() && find_unescaped(code, '=').is_none()
+}
+
+/// Position of the first `separator` that isn't escaped with a backslash
+fn find_unescaped(s: &str, separator: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == separator => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternKind {
+    /// A file extension, such as `.tar` or `.tar.gz`
+    Extension,
+    /// Any other pattern, such as `Makefile`, `README*` or `~`
+    Glob,
+}
+
+impl PatternKind {
+    /// Works out the kind of a pattern stored in `LsColours::extensions`
+    pub fn of(pattern: &str) -> PatternKind {
+        match pattern.strip_prefix('.') {
+            Some(ext) if !ext.is_empty() && !ext.contains(['*', '?', '[', '/']) => {
+                PatternKind::Extension
+            }
+            _ => PatternKind::Glob,
+        }
+    }
+
+    /// Turns what was typed for a new rule into the pattern stored in `LsColours::extensions`
+    pub fn normalise(&self, input: &str) -> String {
+        let input = input.trim();
+        let input = input.strip_prefix('*').unwrap_or(input);
+        match self {
+            PatternKind::Extension => format!(".{}", input.trim_start_matches('.')),
+            PatternKind::Glob => input.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for PatternKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternKind::Extension => write!(f, "Extension"),
+            PatternKind::Glob => write!(f, "Pattern"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// A pair without an `=` separating key and value
+    MalformedPair(String),
+    /// A key that ls doesn't understand
+    UnknownKey(String),
+    /// A value that isn't a valid SGR sequence
+    InvalidSequence { key: String, sequence: String },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MalformedPair(pair) => {
+                write!(f, "Expected \'key=value\', found \'{}\'", pair)
+            }
+            ParseError::UnknownKey(key) => write!(f, "Unknown key \'{}\'", key),
+            ParseError::InvalidSequence { key, sequence } => {
+                write!(
+                    f,
+                    "Invalid colour sequence \'{}\' for \'{}\'",
+                    sequence, key
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize,
+)]
 pub struct ColourEntry {
     pub effects: Effects,
     pub fg: Option<TerminalColour>,
@@ -103,57 +750,63 @@ impl ColourEntry {
         Self { effects, fg, bg }
     }
 
+    /// Reads an SGR sequence such as `01;04;38;5;208`, returning `None` if it contains a code
+    /// that can't be represented
+    pub fn from_sgr(sequence: &str) -> Option<Self> {
+        let mut entry = ColourEntry::new(Effects::NONE, None, None);
+        let mut codes = sequence.split(';').map(|x| match x {
+            "" => Some(0),
+            x => x.parse::<u8>().ok(),
+        });
+
+        while let Some(code) = codes.next() {
+            match code? {
+                0 => entry.effects = Effects::NONE,
+                6 => entry.effects.insert(Effect::Blink),
+                n @ 1..=9 => entry
+                    .effects
+                    .insert(Effect::ORDER.into_iter().find(|x| x.code() == n)?),
+                n @ 30..=37 => entry.fg = Some(TerminalColour::ANSI[n as usize - 30]),
+                n @ 90..=97 => entry.fg = Some(TerminalColour::ANSI[n as usize - 90 + 8]),
+                n @ 40..=47 => entry.bg = Some(TerminalColour::ANSI[n as usize - 40]),
+                n @ 100..=107 => entry.bg = Some(TerminalColour::ANSI[n as usize - 100 + 8]),
+                39 => entry.fg = None,
+                49 => entry.bg = None,
+                n @ (38 | 48) => {
+                    let colour = match codes.next()?? {
+                        5 => TerminalColour::Indexed(codes.next()??),
+                        2 => TerminalColour::Rgb(codes.next()??, codes.next()??, codes.next()??),
+                        _ => return None,
+                    };
+                    match n {
+                        38 => entry.fg = Some(colour),
+                        _ => entry.bg = Some(colour),
+                    }
+                }
+                _ => return None,
+            }
+        }
+
+        Some(entry)
+    }
+
     pub fn parse(&self) -> String {
         let mut out = String::new();
 
-        out.push_str(match self.effects {
-            Effects::Default => "00;",
-            Effects::Bold => "01;",
-            Effects::Underline => "04;",
-            Effects::Italic => "03;",
-            Effects::Strikethrough => "09;",
-        });
+        if self.effects.is_empty() {
+            out.push_str("00;");
+        }
 
-        if self.fg.is_some() {
-            out.push_str(match self.fg.unwrap() {
-                TerminalColour::Black => "30;",
-                TerminalColour::Red => "31;",
-                TerminalColour::Green => "32;",
-                TerminalColour::Orange => "33;",
-                TerminalColour::Blue => "34;",
-                TerminalColour::Purple => "35;",
-                TerminalColour::Cyan => "36;",
-                TerminalColour::Gray => "37;",
-                TerminalColour::DarkGray => "90;",
-                TerminalColour::LightRed => "91;",
-                TerminalColour::LightGreen => "92;",
-                TerminalColour::Yellow => "93;",
-                TerminalColour::LightBlue => "94;",
-                TerminalColour::LightPurple => "95;",
-                TerminalColour::Turquoise => "96;",
-                TerminalColour::White => "97;",
-            })
+        for effect in self.effects.iter() {
+            out.push_str(&format!("{:02};", effect.code()));
         }
 
-        if self.bg.is_some() {
-            out.push_str(match self.bg.unwrap() {
-                TerminalColour::Black => "40;",
-                TerminalColour::Red => "41;",
-                TerminalColour::Green => "42;",
-                TerminalColour::Orange => "43;",
-                TerminalColour::Blue => "44;",
-                TerminalColour::Purple => "45;",
-                TerminalColour::Cyan => "46;",
-                TerminalColour::Gray => "47;",
-                TerminalColour::DarkGray => "100;",
-                TerminalColour::LightRed => "101;",
-                TerminalColour::LightGreen => "102;",
-                TerminalColour::Yellow => "103;",
-                TerminalColour::LightBlue => "104;",
-                TerminalColour::LightPurple => "105;",
-                TerminalColour::Turquoise => "106;",
-                TerminalColour::White => "107;",
-            })
+        if let Some(fg) = self.fg {
+            out.push_str(&format!("{};", fg.fg_code()));
+        }
+
+        if let Some(bg) = self.bg {
+            out.push_str(&format!("{};", bg.bg_code()));
         }
 
         // Remove trailing semicolon
@@ -164,26 +817,153 @@ impl ColourEntry {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
-pub enum Effects {
-    Default,
+pub enum Effect {
     Bold,
-    Underline,
+    Dim,
     Italic,
+    Underline,
+    Blink,
+    Reverse,
+    Hidden,
     Strikethrough,
 }
 
-impl Effects {
-    pub const ORDER: [Effects; 5] = [
-        Effects::Default,
-        Effects::Bold,
-        Effects::Underline,
-        Effects::Italic,
-        Effects::Strikethrough,
+impl Effect {
+    pub const ORDER: [Effect; 8] = [
+        Effect::Bold,
+        Effect::Dim,
+        Effect::Italic,
+        Effect::Underline,
+        Effect::Blink,
+        Effect::Reverse,
+        Effect::Hidden,
+        Effect::Strikethrough,
     ];
+
+    /// SGR code that enables the effect
+    pub fn code(&self) -> u8 {
+        match self {
+            Effect::Bold => 1,
+            Effect::Dim => 2,
+            Effect::Italic => 3,
+            Effect::Underline => 4,
+            Effect::Blink => 5,
+            Effect::Reverse => 7,
+            Effect::Hidden => 8,
+            Effect::Strikethrough => 9,
+        }
+    }
+
+    pub fn modifier(&self) -> Modifier {
+        match self {
+            Effect::Bold => Modifier::BOLD,
+            Effect::Dim => Modifier::DIM,
+            Effect::Italic => Modifier::ITALIC,
+            Effect::Underline => Modifier::UNDERLINED,
+            Effect::Blink => Modifier::SLOW_BLINK,
+            Effect::Reverse => Modifier::REVERSED,
+            Effect::Hidden => Modifier::HIDDEN,
+            Effect::Strikethrough => Modifier::CROSSED_OUT,
+        }
+    }
+
+    fn bit(&self) -> u8 {
+        1 << Effect::ORDER.iter().position(|x| x == self).unwrap()
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
+impl std::fmt::Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A combination of text effects, stored in the save file as a list of `Effect`s
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize,
+)]
+#[serde(try_from = "EffectsRepr", into = "Vec<Effect>")]
+pub struct Effects(u8);
+
+impl Effects {
+    pub const NONE: Effects = Effects(0);
+
+    pub fn contains(&self, effect: Effect) -> bool {
+        self.0 & effect.bit() != 0
+    }
+
+    pub fn insert(&mut self, effect: Effect) {
+        self.0 |= effect.bit();
+    }
+
+    pub fn toggle(&mut self, effect: Effect) {
+        self.0 ^= effect.bit();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Effect> + '_ {
+        Effect::ORDER.into_iter().filter(|x| self.contains(*x))
+    }
+
+    pub fn modifier(&self) -> Modifier {
+        self.iter()
+            .fold(Modifier::empty(), |acc, x| acc | x.modifier())
+    }
+}
+
+impl From<Effect> for Effects {
+    fn from(effect: Effect) -> Self {
+        let mut effects = Effects::NONE;
+        effects.insert(effect);
+        effects
+    }
+}
+
+impl From<Effects> for Vec<Effect> {
+    fn from(effects: Effects) -> Self {
+        effects.iter().collect()
+    }
+}
+
+/// Save files written before effects could be combined store a single effect name
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum EffectsRepr {
+    List(Vec<Effect>),
+    Single(String),
+}
+
+impl TryFrom<EffectsRepr> for Effects {
+    type Error = String;
+
+    fn try_from(repr: EffectsRepr) -> Result<Self, Self::Error> {
+        let list = match repr {
+            EffectsRepr::List(list) => list,
+            EffectsRepr::Single(s) => match s.as_str() {
+                "Default" => vec![],
+                _ => vec![Effect::ORDER
+                    .into_iter()
+                    .find(|x| x.to_string() == s)
+                    .ok_or(format!("unknown effect \'{}\'", s))?],
+            },
+        };
+
+        let mut effects = Effects::NONE;
+        for effect in list {
+            effects.insert(effect);
+        }
+        Ok(effects)
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize,
+)]
 pub enum TerminalColour {
+    #[default]
     Black,
     Red,
     Green,
@@ -200,6 +980,13 @@ pub enum TerminalColour {
     LightPurple,
     Turquoise,
     White,
+    /// An entry in the terminal's 256 colour palette
+    Indexed(u8),
+    /// A 24-bit truecolor value
+    Rgb(u8, u8, u8),
+    /// A reference to one of the scheme's `slots`. It has no colour of its own until it is looked
+    /// up with `LsColours::rgb`
+    Slot(u8),
 }
 
 impl TerminalColour {
@@ -221,51 +1008,581 @@ impl TerminalColour {
         TerminalColour::Turquoise,
         TerminalColour::White,
     ];
-}
 
-impl Into<Color> for TerminalColour {
-    fn into(self) -> Color {
+    /// The named colours in the order of their ANSI palette index
+    pub const ANSI: [TerminalColour; 16] = [
+        TerminalColour::Black,
+        TerminalColour::Red,
+        TerminalColour::Green,
+        TerminalColour::Orange,
+        TerminalColour::Blue,
+        TerminalColour::Purple,
+        TerminalColour::Cyan,
+        TerminalColour::Gray,
+        TerminalColour::DarkGray,
+        TerminalColour::LightRed,
+        TerminalColour::LightGreen,
+        TerminalColour::Yellow,
+        TerminalColour::LightBlue,
+        TerminalColour::LightPurple,
+        TerminalColour::Turquoise,
+        TerminalColour::White,
+    ];
+
+    /// Position of the colour in the terminal's 256 colour palette, if it has one
+    pub fn palette_index(&self) -> Option<u8> {
+        match self {
+            TerminalColour::Indexed(n) => Some(*n),
+            TerminalColour::Rgb(..) | TerminalColour::Slot(_) => None,
+            c => TerminalColour::ANSI
+                .iter()
+                .position(|x| x == c)
+                .map(|x| x as u8),
+        }
+    }
+
+    /// Approximate RGB value of the colour, using xterm's default palette for palette colours
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        const ANSI_RGB: [(u8, u8, u8); 16] = [
+            (0x00, 0x00, 0x00),
+            (0xcd, 0x00, 0x00),
+            (0x00, 0xcd, 0x00),
+            (0xcd, 0xcd, 0x00),
+            (0x00, 0x00, 0xee),
+            (0xcd, 0x00, 0xcd),
+            (0x00, 0xcd, 0xcd),
+            (0xe5, 0xe5, 0xe5),
+            (0x7f, 0x7f, 0x7f),
+            (0xff, 0x00, 0x00),
+            (0x00, 0xff, 0x00),
+            (0xff, 0xff, 0x00),
+            (0x5c, 0x5c, 0xff),
+            (0xff, 0x00, 0xff),
+            (0x00, 0xff, 0xff),
+            (0xff, 0xff, 0xff),
+        ];
+        const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+        match self {
+            TerminalColour::Rgb(r, g, b) => (*r, *g, *b),
+            TerminalColour::Slot(_) => (0, 0, 0),
+            c => match c.palette_index().unwrap() {
+                n @ 0..=15 => ANSI_RGB[n as usize],
+                n @ 16..=231 => {
+                    let n = n - 16;
+                    (
+                        CUBE_LEVELS[(n / 36) as usize],
+                        CUBE_LEVELS[(n / 6 % 6) as usize],
+                        CUBE_LEVELS[(n % 6) as usize],
+                    )
+                }
+                n => {
+                    let level = 8 + (n - 232) * 10;
+                    (level, level, level)
+                }
+            },
+        }
+    }
+
+    /// The named colour closest to this one
+    pub fn nearest_named(&self) -> TerminalColour {
+        let (r, g, b) = self.to_rgb();
+        *TerminalColour::ANSI
+            .iter()
+            .min_by_key(|c| {
+                let (cr, cg, cb) = c.to_rgb();
+                (r as i32 - cr as i32).pow(2)
+                    + (g as i32 - cg as i32).pow(2)
+                    + (b as i32 - cb as i32).pow(2)
+            })
+            .unwrap()
+    }
+
+    /// Relative luminance as defined by WCAG 2, from 0 for black to 1 for white
+    pub fn luminance(&self) -> f64 {
+        let (r, g, b) = self.to_rgb();
+        0.2126 * to_linear(r) + 0.7152 * to_linear(g) + 0.0722 * to_linear(b)
+    }
+
+    /// WCAG contrast ratio between two colours, from 1 for identical colours to 21 for black
+    /// and white
+    pub fn contrast(&self, other: &TerminalColour) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// The colour in CIELAB, under a D65 white point
+    pub fn to_lab(&self) -> (f64, f64, f64) {
+        let (r, g, b) = self.to_rgb();
+        let (r, g, b) = (to_linear(r), to_linear(g), to_linear(b));
+        let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+        let f = |t: f64| match t > 216.0 / 24389.0 {
+            true => t.cbrt(),
+            false => (24389.0 / 27.0 * t + 16.0) / 116.0,
+        };
+        (
+            116.0 * f(y) - 16.0,
+            500.0 * (f(x) - f(y)),
+            200.0 * (f(y) - f(z)),
+        )
+    }
+
+    /// Perceptual difference between two colours using CIEDE2000. Around 2 is barely noticeable,
+    /// while anything past 10 can be told apart at a glance
+    pub fn distance(&self, other: &TerminalColour) -> f64 {
+        let (l1, a1, b1) = self.to_lab();
+        let (l2, a2, b2) = other.to_lab();
+
+        let c_bar = ((a1.powi(2) + b1.powi(2)).sqrt() + (a2.powi(2) + b2.powi(2)).sqrt()) / 2.0;
+        let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + 25f64.powi(7))).sqrt());
+        let (a1, a2) = (a1 * (1.0 + g), a2 * (1.0 + g));
+        let (c1, c2) = (
+            (a1.powi(2) + b1.powi(2)).sqrt(),
+            (a2.powi(2) + b2.powi(2)).sqrt(),
+        );
+        let hue = |b: f64, a: f64| match b == 0.0 && a == 0.0 {
+            true => 0.0,
+            false => b.atan2(a).to_degrees().rem_euclid(360.0),
+        };
+        let (h1, h2) = (hue(b1, a1), hue(b2, a2));
+
+        let dl = l2 - l1;
+        let dc = c2 - c1;
+        let dh = match c1 * c2 == 0.0 {
+            true => 0.0,
+            false => match h2 - h1 {
+                d if d > 180.0 => d - 360.0,
+                d if d < -180.0 => d + 360.0,
+                d => d,
+            },
+        };
+        let dh = 2.0 * (c1 * c2).sqrt() * (dh / 2.0).to_radians().sin();
+
+        let l_bar = (l1 + l2) / 2.0;
+        let c_bar = (c1 + c2) / 2.0;
+        let h_bar = match c1 * c2 == 0.0 {
+            true => h1 + h2,
+            false if (h1 - h2).abs() <= 180.0 => (h1 + h2) / 2.0,
+            false if h1 + h2 < 360.0 => (h1 + h2 + 360.0) / 2.0,
+            false => (h1 + h2 - 360.0) / 2.0,
+        };
+
+        let t = 1.0 - 0.17 * (h_bar - 30.0).to_radians().cos()
+            + 0.24 * (2.0 * h_bar).to_radians().cos()
+            + 0.32 * (3.0 * h_bar + 6.0).to_radians().cos()
+            - 0.20 * (4.0 * h_bar - 63.0).to_radians().cos();
+        let sl = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+        let sc = 1.0 + 0.045 * c_bar;
+        let sh = 1.0 + 0.015 * c_bar * t;
+        let rt = -2.0
+            * (c_bar.powi(7) / (c_bar.powi(7) + 25f64.powi(7))).sqrt()
+            * (60.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp())
+                .to_radians()
+                .sin();
+
+        ((dl / sl).powi(2) + (dc / sc).powi(2) + (dh / sh).powi(2) + rt * (dc / sc) * (dh / sh))
+            .sqrt()
+    }
+
+    /// Parses a `#rrggbb` or `rrggbb` hex string into a truecolor value
+    pub fn from_hex(hex: &str) -> Option<TerminalColour> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Some(TerminalColour::Rgb(channel(0), channel(2), channel(4)))
+    }
+
+    pub fn to_hex(&self) -> String {
+        let (r, g, b) = self.to_rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    pub fn fg_code(&self) -> String {
         match self {
-            crate::colours::TerminalColour::Black => Color::Black,
-            crate::colours::TerminalColour::Red => Color::Red,
-            crate::colours::TerminalColour::Green => Color::Green,
-            crate::colours::TerminalColour::Orange => Color::Yellow,
-            crate::colours::TerminalColour::Blue => Color::Blue,
-            crate::colours::TerminalColour::Purple => Color::Magenta,
-            crate::colours::TerminalColour::Cyan => Color::Cyan,
-            crate::colours::TerminalColour::Gray => Color::Gray,
-            crate::colours::TerminalColour::DarkGray => Color::DarkGray,
-            crate::colours::TerminalColour::LightRed => Color::LightRed,
-            crate::colours::TerminalColour::LightGreen => Color::LightGreen,
-            crate::colours::TerminalColour::Yellow => Color::LightYellow,
-            crate::colours::TerminalColour::LightBlue => Color::LightBlue,
-            crate::colours::TerminalColour::LightPurple => Color::LightMagenta,
-            crate::colours::TerminalColour::Turquoise => Color::LightCyan,
-            crate::colours::TerminalColour::White => Color::White,
-        }
-    }
-}
-
-impl ToString for TerminalColour {
-    fn to_string(&self) -> String {
+            TerminalColour::Indexed(n) => format!("38;5;{}", n),
+            TerminalColour::Rgb(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+            TerminalColour::Slot(_) => "39".to_string(),
+            c => match c.palette_index().unwrap() {
+                n @ 0..=7 => format!("{}", 30 + n),
+                n => format!("{}", 90 + n - 8),
+            },
+        }
+    }
+
+    pub fn bg_code(&self) -> String {
         match self {
-            TerminalColour::Black => "Black",
-            TerminalColour::Red => "Red",
-            TerminalColour::Green => "Green",
-            TerminalColour::Orange => "Orange",
-            TerminalColour::Blue => "Blue",
-            TerminalColour::Purple => "Purple",
-            TerminalColour::Cyan => "Cyan",
-            TerminalColour::Gray => "Gray",
-            TerminalColour::DarkGray => "DarkGray",
-            TerminalColour::LightRed => "LightRed",
-            TerminalColour::LightGreen => "LightGreen",
-            TerminalColour::Yellow => "Yellow",
-            TerminalColour::LightBlue => "LightBlue",
-            TerminalColour::LightPurple => "LightPurple",
-            TerminalColour::Turquoise => "Turquoise",
-            TerminalColour::White => "White",
-        }
-        .to_string()
+            TerminalColour::Indexed(n) => format!("48;5;{}", n),
+            TerminalColour::Rgb(r, g, b) => format!("48;2;{};{};{}", r, g, b),
+            TerminalColour::Slot(_) => "49".to_string(),
+            c => match c.palette_index().unwrap() {
+                n @ 0..=7 => format!("{}", 40 + n),
+                n => format!("{}", 100 + n - 8),
+            },
+        }
+    }
+}
+
+/// Converts an sRGB channel to linear light, from 0 to 1
+fn to_linear(channel: u8) -> f64 {
+    let c = channel as f64 / 255.0;
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn from_linear(c: f64) -> u8 {
+    let c = match c.clamp(0.0, 1.0) {
+        c if c <= 0.0031308 => c * 12.92,
+        c => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+    };
+    (c * 255.0).round() as u8
+}
+
+/// A colour in hue (degrees), saturation and lightness (0 to 1)
+#[derive(Debug, Clone, Copy)]
+pub struct Hsl {
+    pub h: f64,
+    pub s: f64,
+    pub l: f64,
+}
+
+impl Hsl {
+    pub fn from_rgb((r, g, b): (u8, u8, u8)) -> Hsl {
+        let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.0;
+        let d = max - min;
+
+        if d == 0.0 {
+            return Hsl { h: 0.0, s: 0.0, l };
+        }
+
+        let s = d / (1.0 - (2.0 * l - 1.0).abs());
+        let h = match max {
+            x if x == r => 60.0 * ((g - b) / d).rem_euclid(6.0),
+            x if x == g => 60.0 * ((b - r) / d + 2.0),
+            _ => 60.0 * ((r - g) / d + 4.0),
+        };
+        Hsl { h, s, l }
+    }
+
+    pub fn to_colour(self) -> TerminalColour {
+        let c = (1.0 - (2.0 * self.l - 1.0).abs()) * self.s;
+        let x = c * (1.0 - ((self.h / 60.0).rem_euclid(2.0) - 1.0).abs());
+        let m = self.l - c / 2.0;
+        let (r, g, b) = match self.h.rem_euclid(360.0) {
+            h if h < 60.0 => (c, x, 0.0),
+            h if h < 120.0 => (x, c, 0.0),
+            h if h < 180.0 => (0.0, c, x),
+            h if h < 240.0 => (0.0, x, c),
+            h if h < 300.0 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let channel = |v: f64| ((v + m).clamp(0.0, 1.0) * 255.0).round() as u8;
+        TerminalColour::Rgb(channel(r), channel(g), channel(b))
+    }
+
+    /// Lightens or darkens the colour until it has at least `min` contrast against `other`
+    pub fn readable(mut self, other: TerminalColour, min: f64, lighten: bool) -> TerminalColour {
+        while self.to_colour().contrast(&other) < min && (0.0..=1.0).contains(&self.l) {
+            self.l += match lighten {
+                true => 0.01,
+                false => -0.01,
+            };
+        }
+        self.l = self.l.clamp(0.0, 1.0);
+        self.to_colour()
+    }
+}
+
+/// A kind of colour vision the scheme can be previewed with
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Vision {
+    #[default]
+    Normal,
+    /// No red cones
+    Protanopia,
+    /// No green cones
+    Deuteranopia,
+    /// No blue cones
+    Tritanopia,
+}
+
+impl Vision {
+    pub const ORDER: [Vision; 4] = [
+        Vision::Normal,
+        Vision::Protanopia,
+        Vision::Deuteranopia,
+        Vision::Tritanopia,
+    ];
+
+    /// The vision after this one, wrapping back to normal
+    pub fn next(&self) -> Vision {
+        let x = Vision::ORDER.iter().position(|x| x == self).unwrap();
+        Vision::ORDER[(x + 1) % Vision::ORDER.len()]
+    }
+
+    /// How a colour looks with this vision, using the full severity matrices from Machado,
+    /// Oliveira and Fernandes (2009). Palette colours are simulated from xterm's defaults, so the
+    /// result is always truecolor
+    pub fn simulate(&self, colour: TerminalColour) -> TerminalColour {
+        let matrix = match self {
+            Vision::Normal => return colour,
+            Vision::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Vision::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Vision::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.307900],
+            ],
+        };
+
+        let (r, g, b) = colour.to_rgb();
+        let rgb = [to_linear(r), to_linear(g), to_linear(b)];
+        let channel = |row: [f64; 3]| from_linear(row.iter().zip(rgb).map(|(m, c)| m * c).sum());
+        TerminalColour::Rgb(channel(matrix[0]), channel(matrix[1]), channel(matrix[2]))
+    }
+
+    /// Simulates both colours of an entry
+    pub fn simulate_entry(&self, entry: ColourEntry) -> ColourEntry {
+        ColourEntry {
+            fg: entry.fg.map(|x| self.simulate(x)),
+            bg: entry.bg.map(|x| self.simulate(x)),
+            ..entry
+        }
+    }
+}
+
+impl std::fmt::Display for Vision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<TerminalColour> for Color {
+    fn from(colour: TerminalColour) -> Color {
+        match colour {
+            TerminalColour::Black => Color::Black,
+            TerminalColour::Red => Color::Red,
+            TerminalColour::Green => Color::Green,
+            TerminalColour::Orange => Color::Yellow,
+            TerminalColour::Blue => Color::Blue,
+            TerminalColour::Purple => Color::Magenta,
+            TerminalColour::Cyan => Color::Cyan,
+            TerminalColour::Gray => Color::Gray,
+            TerminalColour::DarkGray => Color::DarkGray,
+            TerminalColour::LightRed => Color::LightRed,
+            TerminalColour::LightGreen => Color::LightGreen,
+            TerminalColour::Yellow => Color::LightYellow,
+            TerminalColour::LightBlue => Color::LightBlue,
+            TerminalColour::LightPurple => Color::LightMagenta,
+            TerminalColour::Turquoise => Color::LightCyan,
+            TerminalColour::White => Color::White,
+            TerminalColour::Indexed(n) => Color::Indexed(n),
+            TerminalColour::Slot(_) => Color::Reset,
+            TerminalColour::Rgb(r, g, b) => Color::Rgb(r, g, b),
+        }
+    }
+}
+
+impl std::fmt::Display for TerminalColour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TerminalColour::Black => write!(f, "Black"),
+            TerminalColour::Red => write!(f, "Red"),
+            TerminalColour::Green => write!(f, "Green"),
+            TerminalColour::Orange => write!(f, "Orange"),
+            TerminalColour::Blue => write!(f, "Blue"),
+            TerminalColour::Purple => write!(f, "Purple"),
+            TerminalColour::Cyan => write!(f, "Cyan"),
+            TerminalColour::Gray => write!(f, "Gray"),
+            TerminalColour::DarkGray => write!(f, "DarkGray"),
+            TerminalColour::LightRed => write!(f, "LightRed"),
+            TerminalColour::LightGreen => write!(f, "LightGreen"),
+            TerminalColour::Yellow => write!(f, "Yellow"),
+            TerminalColour::LightBlue => write!(f, "LightBlue"),
+            TerminalColour::LightPurple => write!(f, "LightPurple"),
+            TerminalColour::Turquoise => write!(f, "Turquoise"),
+            TerminalColour::White => write!(f, "White"),
+            TerminalColour::Indexed(n) => write!(f, "Index {}", n),
+            TerminalColour::Slot(n) => write!(f, "Slot {}", n),
+            TerminalColour::Rgb(..) => write!(f, "{}", self.to_hex()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What GNU coreutils sets `LS_COLORS` to by default, shortened to a few patterns
+    const GNU: &str = "rs=0:di=01;34:ln=01;36:mh=00:pi=40;33:so=01;35:do=01;35:bd=40;33;01:\
+        cd=40;33;01:or=40;31;01:mi=00:su=37;41:sg=30;43:ca=00:tw=30;42:ow=34;42:st=37;44:\
+        ex=01;32:*.tar=01;31:*.jpg=01;35:*.flac=00;36";
+
+    fn pairs(s: &str) -> Vec<(String, ColourEntry)> {
+        s.split(':')
+            .map(|x| x.split_once('=').unwrap())
+            .map(|(key, value)| (key.to_string(), ColourEntry::from_sgr(value).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let colours: LsColours = GNU.parse().unwrap();
+        assert_eq!(colours.parse().parse::<LsColours>().unwrap(), colours);
+
+        // Every key comes back with the same codes, and the keys GNU leaves out stay uncoloured
+        let exported = pairs(&colours.parse());
+        for pair in pairs(GNU) {
+            assert!(exported.contains(&pair), "{} changed", pair.0);
+        }
+        for (key, entry) in exported {
+            if !pairs(GNU).iter().any(|x| x.0 == key) {
+                assert_eq!(entry, ColourEntry::default(), "{} was coloured", key);
+            }
+        }
+    }
+
+    #[test]
+    fn absent_keys_are_uncoloured() {
+        let colours: LsColours = "di=01;34".parse().unwrap();
+        assert_eq!(colours.norm, ColourEntry::default());
+        assert_eq!(colours.file, ColourEntry::default());
+        assert_eq!(
+            colours.dir,
+            ColourEntry::new(Effect::Bold.into(), Some(TerminalColour::Blue), None)
+        );
+    }
+
+    #[test]
+    fn link_target() {
+        let colours: LsColours = "ln=target:di=01;34".parse().unwrap();
+        assert!(colours.link_target);
+        assert!(colours.parse().contains("ln=target:"));
+    }
+
+    #[test]
+    fn multiple_codes() {
+        let mut effects = Effects::from(Effect::Bold);
+        effects.insert(Effect::Underline);
+        assert_eq!(
+            ColourEntry::from_sgr("01;04;31;42"),
+            Some(ColourEntry::new(
+                effects,
+                Some(TerminalColour::Red),
+                Some(TerminalColour::Green)
+            ))
+        );
+        // A reset drops the effects before it
+        assert_eq!(
+            ColourEntry::from_sgr("01;0;33"),
+            Some(ColourEntry::new(
+                Effects::NONE,
+                Some(TerminalColour::Orange),
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn extended_colours() {
+        let entry = ColourEntry::from_sgr("38;5;208;48;2;1;2;3").unwrap();
+        assert_eq!(entry.fg, Some(TerminalColour::Indexed(208)));
+        assert_eq!(entry.bg, Some(TerminalColour::Rgb(1, 2, 3)));
+        assert_eq!(ColourEntry::from_sgr(&entry.parse()), Some(entry));
+
+        assert_eq!(ColourEntry::from_sgr("38;5"), None);
+        assert_eq!(ColourEntry::from_sgr("38;2;1;2"), None);
+        assert_eq!(ColourEntry::from_sgr("38;5;256"), None);
+    }
+
+    #[test]
+    fn inherited_group_patterns() {
+        let group = |entry, patterns: &[&str], inherit_entry| ExtensionGroup {
+            name: "Archives".to_string(),
+            entry,
+            patterns: patterns.iter().map(|x| x.to_string()).collect(),
+            inherit_entry,
+        };
+        let red = ColourEntry::new(Effects::NONE, Some(TerminalColour::Red), None);
+        let blue = ColourEntry::new(Effects::NONE, Some(TerminalColour::Blue), None);
+
+        let mut parent = LsColours::uncoloured();
+        parent.groups = vec![group(red, &[".tar"], false)];
+        let mut child = LsColours::uncoloured();
+        child.parent = Some("parent".to_string());
+        child.groups = vec![group(red, &[".zst"], true)];
+
+        // A colour changed in the parent reaches the patterns the child added
+        parent.groups[0].entry = blue;
+        assert_eq!(
+            child.inherit(&parent).groups,
+            vec![group(blue, &[".tar", ".zst"], false)]
+        );
+
+        child.groups[0].inherit_entry = false;
+        assert_eq!(child.inherit(&parent).groups[0].entry, red);
+    }
+
+    #[test]
+    fn unset_inherited_escape() {
+        let mut parent = LsColours::uncoloured();
+        parent.left_code = Some("\\e[".to_string());
+        let mut child = LsColours::uncoloured();
+        child.parent = Some("parent".to_string());
+        assert_eq!(child.inherit(&parent).left_code, parent.left_code);
+
+        child.set_override("lc");
+        assert_eq!(child.inherit(&parent).left_code, None);
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(!valid_escape("\\e[1:2m"));
+        assert!(!valid_escape("\\e[=m"));
+        assert!(valid_escape("\\e[1\\:2m"));
+
+        let mut colours = LsColours::uncoloured();
+        colours.left_code = Some("\\e[1\\:2\\=".to_string());
+        colours.right_code = Some("m".to_string());
+        let parsed = colours.parse().parse::<LsColours>().unwrap();
+        assert_eq!(parsed.left_code, colours.left_code);
+        assert_eq!(parsed.right_code, colours.right_code);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "di".parse::<LsColours>(),
+            Err(ParseError::MalformedPair("di".to_string()))
+        );
+        assert_eq!(
+            "di=01:xx=01".parse::<LsColours>(),
+            Err(ParseError::UnknownKey("xx".to_string()))
+        );
+        assert_eq!(
+            "di=38;5".parse::<LsColours>(),
+            Err(ParseError::InvalidSequence {
+                key: "di".to_string(),
+                sequence: "38;5".to_string()
+            })
+        );
     }
 }
@@ -0,0 +1,316 @@
+use crate::colours::{escape_pattern, ColourEntry, LsColours};
+
+/// dircolors keywords and the `LS_COLORS` keys they set. Where a key has several keywords the
+/// first is the one dircolors itself prints
+const KEYWORDS: [(&str, &str); 37] = [
+    ("NORMAL", "no"),
+    ("NORM", "no"),
+    ("FILE", "fi"),
+    ("RESET", "rs"),
+    ("DIR", "di"),
+    ("LINK", "ln"),
+    ("LNK", "ln"),
+    ("SYMLINK", "ln"),
+    ("MULTIHARDLINK", "mh"),
+    ("FIFO", "pi"),
+    ("PIPE", "pi"),
+    ("SOCK", "so"),
+    ("DOOR", "do"),
+    ("BLK", "bd"),
+    ("BLOCK", "bd"),
+    ("CHR", "cd"),
+    ("CHAR", "cd"),
+    ("ORPHAN", "or"),
+    ("MISSING", "mi"),
+    ("SETUID", "su"),
+    ("SUID", "su"),
+    ("SETGID", "sg"),
+    ("SGID", "sg"),
+    ("CAPABILITY", "ca"),
+    ("STICKY_OTHER_WRITABLE", "tw"),
+    ("OWT", "tw"),
+    ("OTHER_WRITABLE", "ow"),
+    ("OWR", "ow"),
+    ("STICKY", "st"),
+    ("EXEC", "ex"),
+    ("LEFTCODE", "lc"),
+    ("LEFT", "lc"),
+    ("RIGHTCODE", "rc"),
+    ("RIGHT", "rc"),
+    ("ENDCODE", "ec"),
+    ("END", "ec"),
+    ("CLRTOEOL", "cl"),
+];
+
+/// Keys whose values are raw escape sequences rather than SGR codes
+const RAW_KEYS: [&str; 4] = ["lc", "rc", "ec", "cl"];
+
+/// Keywords that only change how dircolors behaves, rather than setting a colour
+const IGNORED_KEYWORDS: [&str; 5] = ["TERM", "COLORTERM", "OPTIONS", "COLOR", "EIGHTBIT"];
+
+/// Reads a dircolors database, such as `~/.dircolors` or the output of `dircolors -p`.
+/// `TERM` and `COLORTERM` sections are ignored, so every entry in the file ends up in the scheme
+pub fn parse(contents: &str) -> Result<LsColours, String> {
+    let mut pairs = vec![];
+
+    for (i, line) in contents.lines().enumerate() {
+        let mut words = line.split_whitespace().take_while(|x| !x.starts_with('#'));
+
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if IGNORED_KEYWORDS.contains(&keyword.to_uppercase().as_str()) {
+            continue;
+        }
+
+        let value = words
+            .next()
+            .ok_or(format!("Line {}: \'{}\' has no value", i + 1, keyword))?;
+
+        let key = if keyword.starts_with('.') {
+            format!("*{}", quote(keyword))
+        } else if let Some(pattern) = keyword.strip_prefix('*') {
+            format!("*{}", quote(pattern))
+        } else {
+            match KEYWORDS.iter().find(|x| x.0.eq_ignore_ascii_case(keyword)) {
+                Some(x) => x.1.to_string(),
+                None => return Err(format!("Line {}: Unknown keyword \'{}\'", i + 1, keyword)),
+            }
+        };
+
+        if ColourEntry::from_sgr(value).is_none()
+            && !(key == "ln" && value == "target")
+            && !RAW_KEYS.contains(&key.as_str())
+        {
+            return Err(format!(
+                "Line {}: Invalid colour sequence \'{}\' for \'{}\'",
+                i + 1,
+                value,
+                keyword
+            ));
+        }
+
+        pairs.push(format!("{}={}", key, quote(value)));
+    }
+
+    pairs.join(":").parse().map_err(|e| format!("{}", e))
+}
+
+/// Writes a scheme as a dircolors database that can be passed to `dircolors -b`
+pub fn export(colours: &LsColours) -> String {
+    let colours = &colours.with_slots_resolved();
+    let entries = [
+        ("NORMAL", colours.norm, "global default"),
+        ("FILE", colours.file, "normal file"),
+        ("RESET", colours.rs, "reset to \"normal\" colour"),
+        ("DIR", colours.dir, "directory"),
+        ("LINK", colours.link, "symbolic link"),
+        ("FIFO", colours.pipe, "pipe"),
+        ("SOCK", colours.sock, "socket"),
+        ("DOOR", colours.door, "door"),
+        ("BLK", colours.block, "block device driver"),
+        ("CHR", colours.char, "character device driver"),
+        ("ORPHAN", colours.orphan, "symlink to nonexistent file"),
+        (
+            "MISSING",
+            colours.missing,
+            "nonexistent file pointed to by a symlink",
+        ),
+        ("SETUID", colours.setuid, "file that is setuid (u+s)"),
+        ("SETGID", colours.setgid, "file that is setgid (g+s)"),
+        (
+            "STICKY_OTHER_WRITABLE",
+            colours.sticky_other_writable,
+            "dir that is sticky and other-writable (+t,o+w)",
+        ),
+        (
+            "OTHER_WRITABLE",
+            colours.other_writable,
+            "dir that is other-writable (o+w) and not sticky",
+        ),
+        (
+            "STICKY",
+            colours.sticky,
+            "dir with the sticky bit set (+t) and not other-writable",
+        ),
+        ("EXEC", colours.exec, "executable file"),
+        (
+            "MULTIHARDLINK",
+            colours.multi_hardlink,
+            "regular file with more than one link",
+        ),
+        (
+            "CAPABILITY",
+            colours.capability,
+            "file with capability (very expensive to lookup)",
+        ),
+    ];
+    let escapes = [
+        ("LEFTCODE", &colours.left_code),
+        ("RIGHTCODE", &colours.right_code),
+        ("ENDCODE", &colours.end_code),
+        ("CLRTOEOL", &colours.clear_line),
+    ];
+
+    let mut out = String::new();
+    out.push_str("# Configuration file for dircolors, generated by lscoltui\n");
+    out.push_str("# Load it with: eval \"$(dircolors -b <file>)\"\n");
+    out.push_str("\n# File types\n");
+
+    for (keyword, entry, comment) in entries {
+        let value = match keyword == "LINK" && colours.link_target {
+            true => "target".to_string(),
+            false => entry.parse(),
+        };
+        out.push_str(&format!("{} {} # {}\n", keyword, value, comment));
+    }
+
+    if escapes.iter().any(|x| x.1.is_some()) {
+        out.push_str("\n# Escape sequences\n");
+    }
+
+    for (keyword, code) in escapes {
+        if let Some(code) = code {
+            out.push_str(&format!("{} {}\n", keyword, code));
+        }
+    }
+
+    for group in &colours.groups {
+        out.push_str(&format!("\n# {}\n", group.name));
+        for pattern in &group.patterns {
+            out.push_str(&pattern_line(pattern, &group.entry));
+        }
+    }
+
+    if !colours.extensions.is_empty() {
+        out.push_str("\n# Extensions and patterns\n");
+    }
+
+    for (ext, entry) in &colours.extensions {
+        out.push_str(&pattern_line(ext, entry));
+    }
+
+    // Remove trailing newline
+    out.pop();
+
+    out
+}
+
+fn pattern_line(pattern: &str, entry: &ColourEntry) -> String {
+    // Extensions starting with a dot are written the way dircolors prints them, everything else
+    // keeps the same meaning as it has in LS_COLORS. dircolors passes backslashes through to ls,
+    // so patterns are escaped the same way as there
+    match pattern.starts_with('.') {
+        true => format!("{} {}\n", escape_pattern(pattern), entry.parse()),
+        false => format!("*{} {}\n", escape_pattern(pattern), entry.parse()),
+    }
+}
+
+/// Escapes a keyword or value the way dircolors does when it writes it into `LS_COLORS`. Only
+/// `:` and `=` that aren't already escaped get a backslash, and everything else, backslashes
+/// included, is passed through for ls to read
+fn quote(s: &str) -> String {
+    let mut out = String::new();
+    let mut escaped = false;
+    for c in s.chars() {
+        match c {
+            '\\' | '^' => escaped = !escaped,
+            ':' | '=' => {
+                if !escaped {
+                    out.push('\\');
+                }
+                escaped = false;
+            }
+            _ => escaped = false,
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colours::{Effect, TerminalColour};
+
+    /// An excerpt of `dircolors -p` from coreutils 9.1
+    const DEFAULTS: &str = "\
+# Configuration file for dircolors, a utility to help you set the
+# LS_COLORS environment variable used by GNU ls with the --color option.
+COLORTERM ?*
+TERM Eterm
+TERM xterm*
+#NORMAL 00 # no color code at all
+#FILE 00 # regular file: use no color at all
+RESET 0 # reset to \"normal\" color
+DIR 01;34 # directory
+LINK 01;36 # symbolic link. (If you set this to 'target' instead of a
+ # numerical value, the color is as for the file pointed to.)
+MULTIHARDLINK 00 # regular file with more than one link
+FIFO 40;33 # pipe
+BLK 40;33;01 # block device driver
+ORPHAN 40;31;01 # symlink to nonexistent file, or non-stat'able file ...
+STICKY_OTHER_WRITABLE 30;42 # dir that is sticky and other-writable (+t,o+w)
+# This is for files with execute permission:
+EXEC 01;32
+#.cmd 01;32 # executables (bright green)
+ # archives or compressed (bright red)
+.tar 01;31
+*~ 00;90
+";
+
+    #[test]
+    fn commented_entries_stay_unset() {
+        let colours = parse(DEFAULTS).unwrap();
+        assert_eq!(colours.norm, ColourEntry::default());
+        assert_eq!(colours.file, ColourEntry::default());
+        assert_eq!(
+            colours.dir,
+            ColourEntry::new(Effect::Bold.into(), Some(TerminalColour::Blue), None)
+        );
+        assert_eq!(
+            colours.extensions,
+            vec![
+                (".tar".to_string(), ColourEntry::from_sgr("01;31").unwrap()),
+                ("~".to_string(), ColourEntry::from_sgr("00;90").unwrap()),
+            ]
+        );
+
+        let exported = colours.parse();
+        assert!(exported.starts_with("no=00:rs=00:fi=00:"));
+    }
+
+    #[test]
+    fn link_target() {
+        let colours = parse("LINK target\n").unwrap();
+        assert!(colours.link_target);
+        assert!(export(&colours).contains("LINK target"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let colours = parse(DEFAULTS).unwrap();
+        assert_eq!(parse(&export(&colours)), Ok(colours));
+    }
+
+    #[test]
+    fn escaping() {
+        // dircolors -b turns these into `*a\\b\:c=01;31:*.x\=y=01:lc=\e[1\:2`
+        let colours = parse("*a\\\\b:c 01;31\n.x=y 01\nLEFTCODE \\e[1:2\n").unwrap();
+        assert_eq!(
+            colours.extensions,
+            vec![
+                (
+                    "a\\b:c".to_string(),
+                    ColourEntry::from_sgr("01;31").unwrap()
+                ),
+                (".x=y".to_string(), ColourEntry::from_sgr("01").unwrap()),
+            ]
+        );
+        assert_eq!(colours.left_code, Some("\\e[1\\:2".to_string()));
+        assert!(colours.parse().contains("*a\\\\b\\:c=01;31:*.x\\=y=01"));
+        assert_eq!(parse(&export(&colours)), Ok(colours));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::colours::{escape_pattern, ColourEntry, Effect, Effects, LsColours, TerminalColour};
+
+/// `EZA_COLORS` keys for the parts of a listing that ls doesn't colour, with the names the TUI
+/// uses and a description of each
+pub const KEYS: [(&str, &str, &str); 34] = [
+    ("ur", "User Read", "The owner's read permission bit"),
+    ("uw", "User Write", "The owner's write permission bit"),
+    (
+        "ux",
+        "User Execute",
+        "The owner's execute permission bit on regular files",
+    ),
+    (
+        "ue",
+        "User Execute (Other)",
+        "The owner's execute permission bit on other kinds of file",
+    ),
+    ("gr", "Group Read", "The group's read permission bit"),
+    ("gw", "Group Write", "The group's write permission bit"),
+    ("gx", "Group Execute", "The group's execute permission bit"),
+    ("tr", "Others Read", "Everyone else's read permission bit"),
+    ("tw", "Others Write", "Everyone else's write permission bit"),
+    (
+        "tx",
+        "Others Execute",
+        "Everyone else's execute permission bit",
+    ),
+    (
+        "su",
+        "Special Bits",
+        "The setuid, setgid and sticky permission bits on regular files",
+    ),
+    (
+        "sf",
+        "Special Bits (Other)",
+        "The setuid, setgid and sticky permission bits on other kinds of file",
+    ),
+    (
+        "xa",
+        "Extended Attributes",
+        "The marker for files with extended attributes",
+    ),
+    ("sn", "Size Number", "The number in a file size"),
+    ("sb", "Size Unit", "The unit in a file size"),
+    ("df", "Device Major", "The major ID of a device file"),
+    ("ds", "Device Minor", "The minor ID of a device file"),
+    ("uu", "Your User", "A user that is you"),
+    ("un", "Other User", "A user that isn't you"),
+    ("gu", "Your Group", "A group you belong to"),
+    ("gn", "Other Group", "A group you don't belong to"),
+    ("lc", "Link Count", "The number of hard links to a file"),
+    (
+        "lm",
+        "Multi-Link Count",
+        "The number of hard links to a regular file with more than one",
+    ),
+    ("ga", "Git New", "A file new to git"),
+    (
+        "gm",
+        "Git Modified",
+        "A file modified since the last commit",
+    ),
+    ("gd", "Git Deleted", "A deleted file"),
+    ("gv", "Git Renamed", "A renamed file"),
+    ("gt", "Git Type Change", "A file whose type changed"),
+    (
+        "xx",
+        "Punctuation",
+        "Dashes and other punctuation, such as an unset permission bit",
+    ),
+    ("da", "Date", "Timestamps"),
+    ("in", "Inode", "The inode number of a file"),
+    ("bl", "Blocks", "The number of blocks a file takes up"),
+    ("hd", "Header", "The header row of a table"),
+    ("lp", "Symlink Path", "The path a symlink points to"),
+];
+
+/// eza's own colours for every key in `KEYS`, used when the eza section is first turned on
+pub fn defaults() -> Vec<(String, ColourEntry)> {
+    let plain = |fg| ColourEntry::new(Effects::NONE, Some(fg), None);
+    let bold = |fg| ColourEntry::new(Effect::Bold.into(), Some(fg), None);
+
+    KEYS.iter()
+        .map(|(key, _, _)| {
+            let entry = match *key {
+                "ur" | "uu" | "gu" => bold(TerminalColour::Orange),
+                "uw" | "lc" => bold(TerminalColour::Red),
+                "ux" => {
+                    let mut entry = bold(TerminalColour::Green);
+                    entry.effects.insert(Effect::Underline);
+                    entry
+                }
+                "ue" | "sn" | "df" => bold(TerminalColour::Green),
+                "gr" | "tr" | "gv" => plain(TerminalColour::Orange),
+                "gw" | "tw" | "gd" => plain(TerminalColour::Red),
+                "gx" | "tx" | "sb" | "ds" | "ga" => plain(TerminalColour::Green),
+                "su" | "sf" | "in" | "gt" => plain(TerminalColour::Purple),
+                "lm" => ColourEntry::new(
+                    Effects::NONE,
+                    Some(TerminalColour::Red),
+                    Some(TerminalColour::Orange),
+                ),
+                "gm" | "da" => plain(TerminalColour::Blue),
+                "xx" => plain(TerminalColour::DarkGray),
+                "bl" | "lp" => plain(TerminalColour::Cyan),
+                "hd" => ColourEntry::new(Effect::Underline.into(), None, None),
+                _ => ColourEntry::default(),
+            };
+            (key.to_string(), entry)
+        })
+        .collect()
+}
+
+/// The value of `EZA_COLORS` for a scheme. eza reads the same keys as `LS_COLORS` for kinds of file
+/// and patterns, so those come first, followed by the eza section if there is one. ls keys that eza
+/// reads differently, such as `su` for setuid files, are left out
+pub fn export(colours: &LsColours) -> String {
+    let mut colours = colours.with_slots_resolved();
+    let mut pairs = vec![];
+
+    for (key, entry) in colours.builtins_mut() {
+        if !KEYS.iter().any(|x| x.0 == key) {
+            pairs.push(format!("{}={}", key, entry.parse()));
+        }
+    }
+    for (pattern, entry) in colours.patterns() {
+        pairs.push(format!("*{}={}", escape_pattern(&pattern), entry.parse()));
+    }
+    for (key, entry) in colours.eza_section().into_iter().flatten() {
+        pairs.push(format!("{}={}", key, entry.parse()));
+    }
+
+    pairs.join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheme() -> LsColours {
+        let mut colours = "di=01;34:su=37;41:tw=30;42:*.tar=01;31"
+            .parse::<LsColours>()
+            .unwrap();
+        colours.eza = Some(vec![(
+            "su".to_string(),
+            ColourEntry::new(Effects::NONE, Some(TerminalColour::Purple), None),
+        )]);
+        colours
+    }
+
+    #[test]
+    fn colliding_keys_are_left_out() {
+        let value = export(&scheme());
+        let pairs = value.split(':').collect::<Vec<_>>();
+        assert!(pairs.contains(&"di=01;34"));
+        assert!(pairs.contains(&"*.tar=01;31"));
+        assert!(!pairs.iter().any(|x| x.starts_with("tw=")));
+        // su only comes from the eza section, after everything else
+        assert_eq!(pairs.iter().filter(|x| x.starts_with("su=")).count(), 1);
+        assert_eq!(pairs.last(), Some(&"su=00;35"));
+    }
+
+    #[test]
+    fn disabled_section_is_kept_but_not_exported() {
+        let mut colours = scheme();
+        colours.eza_disabled = true;
+        let value = export(&colours);
+        assert!(!value.split(':').any(|x| x.starts_with("su=")));
+        assert!(colours.eza.is_some());
+    }
+}
@@ -1,4 +1,9 @@
-use crate::colours::{self, LsColours};
+use clap::ValueEnum;
+
+use crate::{
+    colours::{self, LsColours, ParseError},
+    variant,
+};
 
 fn config_path() -> String {
     format!(
@@ -16,7 +21,7 @@ pub struct SaveFile {
 impl SaveFile {
     pub fn load() -> Self {
         if std::path::Path::new(&config_path()).exists() {
-            toml::from_str(&std::fs::read_to_string(&config_path()).unwrap()).unwrap()
+            toml::from_str(&std::fs::read_to_string(config_path()).unwrap()).unwrap()
         } else {
             SaveFile {
                 most_recent: String::new(),
@@ -28,8 +33,264 @@ impl SaveFile {
     pub fn save(&self) -> Result<(), std::io::Error> {
         std::fs::write(config_path(), toml::to_string_pretty(self).unwrap())
     }
+
+    /// A scheme with everything it inherits filled in, following its parents until one has none.
+    /// Variants are derived from their parent again each time, so they follow its changes
+    pub fn resolve(&self, name: &str) -> Result<LsColours, String> {
+        let mut chain = vec![];
+        let mut next = Some(name.to_string());
+
+        while let Some(name) = next {
+            if chain.iter().any(|x: &(String, &LsColours)| x.0 == name) {
+                return Err(format!("\'{}\' inherits from itself", name));
+            }
+            let colours = match self.schemes.iter().find(|x| x.0 == name) {
+                Some(scheme) => &scheme.1,
+                None if chain.is_empty() => {
+                    return Err(format!("Unable to find scheme \'{}\'", name))
+                }
+                None => {
+                    return Err(format!(
+                        "\'{}\' inherits from \'{}\', which doesn\'t exist",
+                        chain.last().unwrap().0,
+                        name
+                    ))
+                }
+            };
+            next = colours.parent.clone();
+            chain.push((name, colours));
+        }
+
+        let mut chain = chain.into_iter().rev();
+        let base = chain.next().unwrap().1.clone();
+        Ok(
+            chain.fold(base, |parent, (_, colours)| match colours.variant {
+                true => colours.inherit(&variant::derive(&parent)),
+                false => colours.inherit(&parent),
+            }),
+        )
+    }
+
+    /// Deletes a scheme. Schemes inheriting from it keep the colours they had by taking a copy of
+    /// everything they inherited
+    pub fn remove(&mut self, index: usize) {
+        let name = self.schemes[index].0.clone();
+        for i in 0..self.schemes.len() {
+            if self.schemes[i].1.parent.as_ref() == Some(&name) {
+                self.detach(i);
+            }
+        }
+        self.schemes.remove(index);
+    }
+
+    /// Stops a scheme inheriting from its parent, keeping the colours it inherited
+    pub fn detach(&mut self, index: usize) {
+        let mut colours = self
+            .resolve(&self.schemes[index].0)
+            .unwrap_or(self.schemes[index].1.clone());
+        colours.parent = None;
+        colours.overrides.clear();
+        colours.variant = false;
+        for group in &mut colours.groups {
+            group.inherit_entry = false;
+        }
+        self.schemes[index].1 = colours;
+    }
+}
+
+/// A shell that `env_command` can write for
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum Shell {
+    #[default]
+    Bash,
+    Zsh,
+    Fish,
+    Nushell,
+    Csh,
+    Tcsh,
+    Elvish,
+    Xonsh,
+}
+
+impl Shell {
+    pub const ORDER: [Shell; 8] = [
+        Shell::Bash,
+        Shell::Zsh,
+        Shell::Fish,
+        Shell::Nushell,
+        Shell::Csh,
+        Shell::Tcsh,
+        Shell::Elvish,
+        Shell::Xonsh,
+    ];
+
+    /// The shell after this one, wrapping back to bash
+    pub fn next(&self) -> Shell {
+        let x = Shell::ORDER.iter().position(|x| x == self).unwrap();
+        Shell::ORDER[(x + 1) % Shell::ORDER.len()]
+    }
+
+    /// How to set the colours of a scheme each time the shell starts
+    pub fn startup_hint(&self, name: &str) -> String {
+        let command = match self {
+            Shell::Bash => format!("lscoltui export {}", name),
+            shell => format!("lscoltui export {} --shell {}", name, shell),
+        };
+        match self {
+            Shell::Bash => format!("place \'eval \"$({})\"\' in ~/.bashrc", command),
+            Shell::Zsh => format!("place \'eval \"$({})\"\' in ~/.zshrc", command),
+            Shell::Fish => format!("place \'{} | source\' in ~/.config/fish/config.fish", command),
+            Shell::Nushell => format!(
+                "save the output of \'{}\' to a file and source it from config.nu, as nushell can\'t run generated code",
+                command
+            ),
+            Shell::Csh => format!("place \'eval `{}`\' in ~/.cshrc", command),
+            Shell::Tcsh => format!("place \'eval `{}`\' in ~/.tcshrc", command),
+            Shell::Elvish => format!("place \'eval ({} | slurp)\' in ~/.config/elvish/rc.elv", command),
+            Shell::Xonsh => format!("place \'execx($({}))\' in ~/.xonshrc", command),
+        }
+    }
 }
 
-pub fn env_command(colours: &LsColours) -> String {
-    format!("export LS_COLORS=\'{}\'", colours.parse())
+impl std::fmt::Display for Shell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_possible_value().unwrap().get_name())
+    }
+}
+
+/// The command that sets `$LS_COLORS` in a shell
+pub fn env_command(colours: &LsColours, shell: Shell) -> String {
+    set_env_command("LS_COLORS", &colours.parse(), shell)
+}
+
+/// The command that sets an environment variable in a shell, quoted so the shell reads the value
+/// as written
+pub fn set_env_command(variable: &str, value: &str, shell: Shell) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => {
+            format!(
+                "export {}=\'{}\'",
+                variable,
+                value.replace('\'', "\'\\\'\'")
+            )
+        }
+        Shell::Fish => format!(
+            "set -gx {} \'{}\'",
+            variable,
+            value.replace('\\', "\\\\").replace('\'', "\\\'")
+        ),
+        Shell::Nushell => format!(
+            "$env.{} = \"{}\"",
+            variable,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        // csh expands history even in single quotes
+        Shell::Csh | Shell::Tcsh => format!(
+            "setenv {} \'{}\'",
+            variable,
+            value.replace('\'', "\'\\\'\'").replace('!', "\\!")
+        ),
+        Shell::Elvish => format!("set-env {} \'{}\'", variable, value.replace('\'', "\'\'")),
+        Shell::Xonsh => format!(
+            "${} = \'{}\'",
+            variable,
+            value.replace('\\', "\\\\").replace('\'', "\\\'")
+        ),
+    }
+}
+
+/// Reads the colours ls is currently using from `$LS_COLORS`
+pub fn env_colours() -> Result<LsColours, String> {
+    let value = std::env::var("LS_COLORS").unwrap_or_default();
+    if value.is_empty() {
+        return Err("LS_COLORS is not set".to_string());
+    }
+
+    value.parse().map_err(|e: ParseError| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colours::ColourEntry;
+
+    /// Reads back the value a command from `set_env_command` sets, following the quoting rules of
+    /// its shell. Panics if the command isn't quoted the way the shell expects
+    fn unquote(command: &str, shell: Shell) -> String {
+        let prefix = match shell {
+            Shell::Bash | Shell::Zsh => "export X=",
+            Shell::Fish => "set -gx X ",
+            Shell::Nushell => "$env.X = ",
+            Shell::Csh | Shell::Tcsh => "setenv X ",
+            Shell::Elvish => "set-env X ",
+            Shell::Xonsh => "$X = ",
+        };
+        let sh = matches!(shell, Shell::Bash | Shell::Zsh | Shell::Csh | Shell::Tcsh);
+        let csh = matches!(shell, Shell::Csh | Shell::Tcsh);
+        // Shells that read backslash escapes inside quotes
+        let escapes = matches!(shell, Shell::Fish | Shell::Nushell | Shell::Xonsh);
+
+        let mut chars = command.strip_prefix(prefix).unwrap().chars().peekable();
+        let mut out = String::new();
+        let mut quote = None;
+        while let Some(c) = chars.next() {
+            match quote {
+                None if c == '\\' && sh => out.push(chars.next().unwrap()),
+                None if c == '\'' || c == '"' => quote = Some(c),
+                None => panic!("{:?}: '{}' outside quotes in {}", shell, c, command),
+                // elvish writes a quote inside quotes twice
+                Some('\'')
+                    if c == '\'' && shell == Shell::Elvish && chars.peek() == Some(&'\'') =>
+                {
+                    out.push(chars.next().unwrap())
+                }
+                Some(q) if c == q => quote = None,
+                Some(_) if c == '\\' && escapes => {
+                    let next = chars.next().unwrap();
+                    assert!(matches!(next, '\\' | '\'' | '"'), "{:?}: \\{}", shell, next);
+                    out.push(next);
+                }
+                Some(_) if c == '\\' && csh && chars.peek() == Some(&'!') => {
+                    out.push(chars.next().unwrap())
+                }
+                Some(_) if c == '!' && csh => panic!("{:?}: '!' would expand history", shell),
+                Some(_) => out.push(c),
+            }
+        }
+        assert_eq!(
+            quote, None,
+            "{:?}: unterminated quote in {}",
+            shell, command
+        );
+        out
+    }
+
+    #[test]
+    fn quoting() {
+        for value in ["lc=\\e[:*it's=01:*$HOME!=31:*\"\\\\=32", "'", "\\"] {
+            for shell in Shell::ORDER {
+                let command = set_env_command("X", value, shell);
+                assert_eq!(unquote(&command, shell), value, "{:?}", shell);
+            }
+        }
+    }
+
+    #[test]
+    fn env_round_trip() {
+        let value = "rs=0:di=01;34:ln=target:tw=30;42:*.tar=01;31:*.tar.gz=01;31";
+        std::env::set_var("LS_COLORS", value);
+        let colours = env_colours();
+        std::env::remove_var("LS_COLORS");
+
+        // Codes are written in a fixed order, so they're compared by what they set
+        let exported = colours.unwrap().parse();
+        let sgr = |s: &str, key: &str| {
+            s.split(':')
+                .find_map(|x| x.strip_prefix(&format!("{}=", key)))
+                .map(|x| ColourEntry::from_sgr(x).ok_or(x.to_string()))
+        };
+        for key in ["rs", "di", "ln", "tw", "*.tar", "*.tar.gz"] {
+            assert_eq!(sgr(&exported, key), sgr(value, key), "{} changed", key);
+        }
+    }
 }
@@ -1,13 +1,34 @@
 use crossterm::event::{KeyCode, KeyEvent};
 
 use crate::{
-    colours::{ColourEntry, Effects, LsColours, TerminalColour},
-    ui::{DialogState, ListColourEntry},
+    check,
+    colours::{
+        valid_escape, ColourEntry, Effect, Effects, ExtensionGroup, LsColours, PatternKind,
+        TerminalColour,
+    },
+    eza, file,
+    presets::PRESETS,
+    ui::{DialogState, EntryKind, ListColourEntry},
+    variant,
 };
 
 use super::App;
 
+/// Foreground, background and one row per effect
+const EDITOR_ROWS: u8 = 2 + Effect::ORDER.len() as u8;
+
 pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
+    app.status = None;
+    let dialog = std::mem::discriminant(&app.dialog_state);
+    handle_key(app, key_event);
+
+    // Hex typed into one dialog mustn't be applied by the next
+    if std::mem::discriminant(&app.dialog_state) != dialog {
+        app.hex_input.clear();
+    }
+}
+
+fn handle_key(app: &mut App, key_event: KeyEvent) {
     match key_event.code {
         KeyCode::F(x) => match x {
             1 => {
@@ -23,13 +44,16 @@ pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
                     DialogState::PickScheme(0)
                 }
             }
-            2 => app.dialog_state = DialogState::NewScheme(String::new()),
-            3 => app.dialog_state = DialogState::NewExtension(String::new()),
-            4 => {
-                if app.open_scheme.is_some() {
-                    app.dialog_state = DialogState::Export
-                }
+            2 => app.dialog_state = DialogState::NewScheme(String::new(), 0),
+            3 => {
+                app.dialog_state = DialogState::NewExtension(String::new(), PatternKind::Extension)
             }
+            6 if app.open_scheme.is_some() => {
+                app.dialog_state = DialogState::NewGroup(String::new())
+            }
+            4 if app.open_scheme.is_some() => app.dialog_state = DialogState::Export,
+            7 if app.open_scheme.is_some() => app.dialog_state = DialogState::EditingBackground,
+            8 if app.open_scheme.is_some() => app.dialog_state = DialogState::EditingSlots(0),
             5 => {
                 if app.open_scheme.is_some() {
                     app.savefile.most_recent = app.open_scheme.clone().unwrap();
@@ -47,10 +71,27 @@ pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
             }
 
             if let DialogState::EditingColour(i) = app.dialog_state {
+                app.hex_input.clear();
                 if i > 0 {
                     app.dialog_state = DialogState::EditingColour(i - 1)
                 } else {
-                    app.dialog_state = DialogState::EditingColour(2)
+                    app.dialog_state = DialogState::EditingColour(EDITOR_ROWS - 1)
+                }
+            }
+
+            if let DialogState::EditingSlots(i) = app.dialog_state {
+                app.hex_input.clear();
+                let slots = active_slots(app).len() as u8;
+                app.dialog_state = DialogState::EditingSlots(match i {
+                    0 => slots.saturating_sub(1),
+                    i => i - 1,
+                })
+            }
+
+            if let DialogState::NewScheme(s, preset) = &app.dialog_state {
+                app.dialog_state = match *preset {
+                    0 => DialogState::NewScheme(s.clone(), PRESETS.len() - 1),
+                    x => DialogState::NewScheme(s.clone(), x - 1),
                 }
             }
 
@@ -66,13 +107,27 @@ pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
             }
 
             if let DialogState::EditingColour(i) = app.dialog_state {
-                if i < 2 {
+                app.hex_input.clear();
+                if i < EDITOR_ROWS - 1 {
                     app.dialog_state = DialogState::EditingColour(i + 1)
                 } else {
                     app.dialog_state = DialogState::EditingColour(0)
                 }
             }
 
+            if let DialogState::EditingSlots(i) = app.dialog_state {
+                app.hex_input.clear();
+                let slots = active_slots(app).len() as u8;
+                app.dialog_state = DialogState::EditingSlots(match i + 1 < slots {
+                    true => i + 1,
+                    false => 0,
+                })
+            }
+
+            if let DialogState::NewScheme(s, preset) = &app.dialog_state {
+                app.dialog_state = DialogState::NewScheme(s.clone(), (preset + 1) % PRESETS.len())
+            }
+
             if let DialogState::Closed = app.dialog_state {
                 app.content_loc += 1;
             }
@@ -84,15 +139,83 @@ pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
             }
         }
         KeyCode::Enter => {
-            if let DialogState::Closed = app.dialog_state {
+            if let DialogState::EditingColour(_) = app.dialog_state {
+                if let Some(c) = TerminalColour::from_hex(&app.hex_input) {
+                    edit_colour(app, |_| Some(c));
+                    app.hex_input.clear();
+                }
+            }
+
+            if let DialogState::EditingBackground = app.dialog_state {
+                if let Some(c) = TerminalColour::from_hex(&app.hex_input) {
+                    edit_background(app, |_| c);
+                    app.hex_input.clear();
+                }
+            }
+
+            if let DialogState::EditingSlots(_) = app.dialog_state {
+                if let Some(c) = TerminalColour::from_hex(&app.hex_input) {
+                    edit_slot(app, |_| c);
+                    app.hex_input.clear();
+                }
+            }
+
+            if let DialogState::NewSlot(s) = &app.dialog_state {
+                let active_scheme = &mut app
+                    .savefile
+                    .schemes
+                    .iter_mut()
+                    .find(|x| &x.0 == app.open_scheme.as_ref().unwrap())
+                    .unwrap()
+                    .1;
+
+                if !s.is_empty()
+                    && !active_scheme.slots.iter().any(|x| &x.0 == s)
+                    && active_scheme.slots.len() < u8::MAX as usize
+                {
+                    active_scheme.slots.push((s.clone(), TerminalColour::White));
+                    app.dialog_state =
+                        DialogState::EditingSlots(active_scheme.slots.len() as u8 - 1);
+                }
+            }
+
+            if let DialogState::EditingEscape(s) = &app.dialog_state {
+                // Left open until the sequence can be saved without breaking LS_COLORS
+                if valid_escape(s) {
+                    if let Some(mut lce) = app.active_lce.clone() {
+                        if let EntryKind::Escape(_, code) = &mut lce.kind {
+                            *code = match s.is_empty() {
+                                true => None,
+                                false => Some(s.clone()),
+                            };
+                        }
+                        let active_scheme = &mut app
+                            .savefile
+                            .schemes
+                            .iter_mut()
+                            .find(|x| &x.0 == app.open_scheme.as_ref().unwrap())
+                            .unwrap()
+                            .1;
+                        update_lce(&lce, active_scheme);
+                        app.active_lce = Some(lce);
+                    }
+                    app.dialog_state = DialogState::Closed;
+                }
+            } else if let DialogState::Closed = app.dialog_state {
                 if app.open_scheme.is_some() {
-                    app.dialog_state = DialogState::EditingColour(0)
+                    app.dialog_state = match &app.active_lce {
+                        Some(ListColourEntry {
+                            kind: EntryKind::Escape(_, code),
+                            ..
+                        }) => DialogState::EditingEscape(code.clone().unwrap_or_default()),
+                        _ => DialogState::EditingColour(0),
+                    }
                 }
             }
 
             if let DialogState::PickScheme(x) = app.dialog_state {
                 if x < app.savefile.schemes.len() as u8 {
-                    if app.savefile.schemes.len() != 0 {
+                    if !app.savefile.schemes.is_empty() {
                         app.open_scheme = Some(app.savefile.schemes[x as usize].0.clone());
                     }
                     app.dialog_state = DialogState::Closed;
@@ -100,142 +223,321 @@ pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
                 }
             }
 
-            if let DialogState::NewScheme(s) = &app.dialog_state {
+            if let DialogState::NewScheme(s, preset) = &app.dialog_state {
                 if !s.is_empty() && app.savefile.schemes.iter().find(|x| &x.0 == s).is_none() {
                     app.open_scheme = Some(s.clone());
-                    app.savefile.schemes.push((s.clone(), LsColours::default()));
+                    app.savefile
+                        .schemes
+                        .push((s.clone(), (PRESETS[*preset].build)()));
                     app.savefile.save().unwrap();
                     app.dialog_state = DialogState::Closed;
                     app.content_loc = 0;
                 }
             }
 
-            if let DialogState::NewExtension(s) = &app.dialog_state {
-                if !s.is_empty() && app.savefile.schemes.iter().find(|x| &x.0 == s).is_none() {
-                    if app.open_scheme.as_ref().is_some() {
-                        app.savefile
-                            .schemes
-                            .iter_mut()
-                            .find(|x| &x.0 == app.open_scheme.as_ref().unwrap())
-                            .unwrap()
-                            .1
-                            .extensions
-                            .push((
-                                s.clone(),
-                                ColourEntry::new(crate::colours::Effects::Default, None, None),
-                            ));
+            if let DialogState::ImportEnv(s) = &app.dialog_state {
+                if !s.is_empty() && !app.savefile.schemes.iter().any(|x| &x.0 == s) {
+                    if let Ok(colours) = file::env_colours() {
+                        app.open_scheme = Some(s.clone());
+                        app.savefile.schemes.push((s.clone(), colours));
+                        app.savefile.save().unwrap();
+                        app.dialog_state = DialogState::Closed;
+                        app.content_loc = 0;
+                    }
+                }
+            }
 
+            if let DialogState::NewExtension(s, kind) = &app.dialog_state {
+                if app.open_scheme.as_ref().is_some() {
+                    let pattern = kind.normalise(s);
+                    let group = app.active_lce.as_ref().and_then(|x| x.group()).cloned();
+                    let inherited = app.resolved_scheme().unwrap();
+                    let active_scheme = &mut app
+                        .savefile
+                        .schemes
+                        .iter_mut()
+                        .find(|x| &x.0 == app.open_scheme.as_ref().unwrap())
+                        .unwrap()
+                        .1;
+
+                    if !s.trim().trim_start_matches(['*', '.']).is_empty()
+                        && check::validate_pattern(&pattern).is_ok()
+                        && !inherited.contains_pattern(group.as_deref(), &pattern)
+                    {
+                        // A group from the parent scheme needs a copy here to add patterns to, which
+                        // keeps taking its colour from the parent
+                        if let Some(group) = inherited
+                            .groups
+                            .iter()
+                            .find(|x| Some(&x.name) == group.as_ref())
+                        {
+                            if !active_scheme.groups.iter().any(|x| x.name == group.name) {
+                                active_scheme.groups.push(ExtensionGroup {
+                                    patterns: vec![],
+                                    inherit_entry: true,
+                                    ..group.clone()
+                                });
+                            }
+                        }
+                        active_scheme.add_pattern(group.as_deref(), pattern);
                         app.dialog_state = DialogState::Closed;
                     }
                 }
             }
+
+            if let DialogState::NewGroup(s) = &app.dialog_state {
+                let active_scheme = &mut app
+                    .savefile
+                    .schemes
+                    .iter_mut()
+                    .find(|x| &x.0 == app.open_scheme.as_ref().unwrap())
+                    .unwrap()
+                    .1;
+
+                if !s.is_empty() && !active_scheme.groups.iter().any(|x| &x.name == s) {
+                    active_scheme.groups.push(ExtensionGroup {
+                        name: s.clone(),
+                        entry: ColourEntry::new(Effects::NONE, None, None),
+                        patterns: vec![],
+                        inherit_entry: false,
+                    });
+                    app.expanded_groups.push(s.clone());
+                    app.dialog_state = DialogState::Closed;
+                }
+            }
+        }
+        KeyCode::Char(' ') if matches!(app.dialog_state, DialogState::Closed) => {
+            if let Some(ListColourEntry {
+                kind: EntryKind::Group(name),
+                ..
+            }) = &app.active_lce
+            {
+                match app.expanded_groups.iter().position(|x| x == name) {
+                    Some(i) => {
+                        app.expanded_groups.remove(i);
+                    }
+                    None => app.expanded_groups.push(name.clone()),
+                }
+            }
         }
         KeyCode::Char(c) => {
-            if let DialogState::NewScheme(s) = &app.dialog_state {
-                app.dialog_state = DialogState::NewScheme(format!("{}{}", s, c))
+            if let DialogState::EditingColour(0..=1)
+            | DialogState::EditingBackground
+            | DialogState::EditingSlots(_) = app.dialog_state
+            {
+                if c == '#' {
+                    app.hex_input = String::from("#");
+                } else if c.is_ascii_hexdigit() && app.hex_input.trim_start_matches('#').len() < 6 {
+                    app.hex_input.push(c.to_ascii_lowercase());
+                }
             }
 
-            if let DialogState::NewExtension(s) = &app.dialog_state {
-                app.dialog_state = DialogState::NewExtension(format!("{}{}", s, c))
+            if let DialogState::NewScheme(s, preset) = &app.dialog_state {
+                app.dialog_state = DialogState::NewScheme(format!("{}{}", s, c), *preset)
+            }
+
+            if let DialogState::NewExtension(s, kind) = &app.dialog_state {
+                app.dialog_state = DialogState::NewExtension(format!("{}{}", s, c), *kind)
+            }
+
+            if let DialogState::EditingEscape(s) = &app.dialog_state {
+                app.dialog_state = DialogState::EditingEscape(format!("{}{}", s, c))
+            }
+
+            if let DialogState::NewGroup(s) = &app.dialog_state {
+                app.dialog_state = DialogState::NewGroup(format!("{}{}", s, c))
+            }
+
+            if let DialogState::ImportEnv(s) = &app.dialog_state {
+                app.dialog_state = DialogState::ImportEnv(format!("{}{}", s, c))
+            }
+
+            if let DialogState::NewSlot(s) = &app.dialog_state {
+                app.dialog_state = DialogState::NewSlot(format!("{}{}", s, c))
+            }
+
+            if let DialogState::Closed = app.dialog_state {
+                if c == 'v' {
+                    app.vision = app.vision.next();
+                } else if c == 'e' {
+                    toggle_eza(app);
+                }
+            }
+
+            if let DialogState::PickScheme(x) = app.dialog_state {
+                if c == 'i' {
+                    app.dialog_state = DialogState::ImportEnv(String::new())
+                } else if c == 'd' {
+                    if let Some(source) = app.savefile.schemes.get(x as usize).map(|x| x.0.clone())
+                    {
+                        if let Ok(colours) = app.savefile.resolve(&source) {
+                            let name = variant::name(&app.savefile, &source, &colours);
+                            app.savefile
+                                .schemes
+                                .push((name.clone(), variant::linked(&source)));
+                            app.savefile.save().unwrap();
+                            app.open_scheme = Some(name);
+                            app.dialog_state = DialogState::Closed;
+                            app.content_loc = 0;
+                        }
+                    }
+                }
+            }
+
+            if let DialogState::EditingSlots(_) = app.dialog_state {
+                if c == '+' {
+                    app.dialog_state = DialogState::NewSlot(String::new())
+                }
             }
         }
         KeyCode::Backspace => {
-            if let DialogState::NewScheme(s) = &app.dialog_state {
+            if let DialogState::EditingColour(_)
+            | DialogState::EditingBackground
+            | DialogState::EditingSlots(_) = app.dialog_state
+            {
+                app.hex_input.pop();
+            }
+
+            if let DialogState::NewScheme(s, preset) = &app.dialog_state {
+                let mut s = s.clone();
+                s.pop();
+                app.dialog_state = DialogState::NewScheme(s, *preset)
+            }
+
+            if let DialogState::NewExtension(s, kind) = &app.dialog_state {
+                let mut s = s.clone();
+                s.pop();
+                app.dialog_state = DialogState::NewExtension(s, *kind)
+            }
+
+            if let DialogState::EditingEscape(s) = &app.dialog_state {
+                let mut s = s.clone();
+                s.pop();
+                app.dialog_state = DialogState::EditingEscape(s)
+            }
+
+            if let DialogState::NewGroup(s) = &app.dialog_state {
                 let mut s = s.clone();
                 s.pop();
-                app.dialog_state = DialogState::NewScheme(format!("{}", s))
+                app.dialog_state = DialogState::NewGroup(s)
             }
 
-            if let DialogState::NewExtension(s) = &app.dialog_state {
+            if let DialogState::ImportEnv(s) = &app.dialog_state {
                 let mut s = s.clone();
                 s.pop();
-                app.dialog_state = DialogState::NewExtension(format!("{}", s))
+                app.dialog_state = DialogState::ImportEnv(s)
+            }
+
+            if let DialogState::NewSlot(s) = &app.dialog_state {
+                let mut s = s.clone();
+                s.pop();
+                app.dialog_state = DialogState::NewSlot(s)
             }
         }
         KeyCode::Delete => {
+            if let DialogState::EditingSlots(i) = app.dialog_state {
+                if let Some(name) = &app.open_scheme {
+                    if let Some(scheme) = app.savefile.schemes.iter_mut().find(|x| &x.0 == name) {
+                        scheme.1.remove_slot(i);
+                        app.hex_input.clear();
+                        app.dialog_state =
+                            DialogState::EditingSlots(i.min(scheme.1.slots.len().max(1) as u8 - 1));
+                    }
+                }
+            }
+
             if let DialogState::PickScheme(x) = app.dialog_state {
                 if app.savefile.schemes.get(x as usize).is_some() {
-                    app.savefile.schemes.remove(x as usize);
+                    app.savefile.remove(x as usize);
                     app.open_scheme = None;
                 }
             }
 
             if let DialogState::Closed = app.dialog_state {
-                if app.open_scheme.is_some() {
-                    let active_scheme = &app
+                if let Some(lce) = &app.active_lce {
+                    let active_scheme = &mut app
                         .savefile
                         .schemes
-                        .iter()
+                        .iter_mut()
                         .find(|x| &x.0 == app.open_scheme.as_ref().unwrap())
                         .unwrap()
                         .1;
 
-                    if app.content_loc >= 18
-                        && app.content_loc < active_scheme.clone().extensions.len() as i32 + 18
-                    {
-                        app.savefile
-                            .schemes
-                            .iter_mut()
-                            .find(|x| &x.0 == app.open_scheme.as_ref().unwrap())
-                            .unwrap()
-                            .1
-                            .extensions
-                            .remove(app.content_loc as usize - 18);
+                    // Rows from the parent aren't stored here, so there is nothing to delete
+                    let (stored, name) = match &lce.kind {
+                        EntryKind::Extension(pattern) => (
+                            active_scheme.extensions.iter().any(|x| &x.0 == pattern),
+                            pattern,
+                        ),
+                        EntryKind::Group(name) => {
+                            (active_scheme.groups.iter().any(|x| &x.name == name), name)
+                        }
+                        EntryKind::GroupMember(name, pattern) => (
+                            active_scheme
+                                .groups
+                                .iter()
+                                .any(|x| &x.name == name && x.patterns.contains(pattern)),
+                            pattern,
+                        ),
+                        _ => (true, &lce.text),
+                    };
+                    if let (false, Some(parent)) = (stored, &active_scheme.parent) {
+                        app.status = Some(format!(
+                            "\'{}\' comes from \'{}\', so it can only be deleted there",
+                            name, parent
+                        ));
+                    }
+
+                    match &lce.kind {
+                        EntryKind::Extension(pattern) => {
+                            active_scheme.extensions.retain(|x| &x.0 != pattern)
+                        }
+                        EntryKind::Group(name) => active_scheme.groups.retain(|x| &x.name != name),
+                        EntryKind::GroupMember(name, pattern) => {
+                            if let Some(group) =
+                                active_scheme.groups.iter_mut().find(|x| &x.name == name)
+                            {
+                                group.patterns.retain(|x| x != pattern);
+                            }
+                        }
+                        _ => {}
                     }
                 }
             }
         }
+        KeyCode::Left if matches!(app.dialog_state, DialogState::EditingBackground) => {
+            edit_background(app, |c| {
+                cycle_colour(Some(c), false, 0).unwrap_or(*TerminalColour::ORDER.last().unwrap())
+            })
+        }
+        KeyCode::Right if matches!(app.dialog_state, DialogState::EditingBackground) => {
+            edit_background(app, |c| {
+                cycle_colour(Some(c), true, 0).unwrap_or(TerminalColour::ORDER[0])
+            })
+        }
+        KeyCode::Left if matches!(app.dialog_state, DialogState::EditingSlots(_)) => {
+            edit_slot(app, |c| {
+                cycle_colour(Some(c), false, 0).unwrap_or(*TerminalColour::ORDER.last().unwrap())
+            })
+        }
+        KeyCode::Right if matches!(app.dialog_state, DialogState::EditingSlots(_)) => {
+            edit_slot(app, |c| {
+                cycle_colour(Some(c), true, 0).unwrap_or(TerminalColour::ORDER[0])
+            })
+        }
         KeyCode::Left => {
             if let DialogState::EditingColour(i) = app.dialog_state {
                 if app.active_lce.clone().is_some() {
+                    let slots = active_slots(app).len();
                     let mut lce = app.active_lce.clone().unwrap();
                     match i {
                         0 => {
-                            lce.entry.fg = match &lce.entry.fg {
-                                Some(c) => {
-                                    if c == TerminalColour::ORDER.first().unwrap() {
-                                        None
-                                    } else {
-                                        let x = TerminalColour::ORDER
-                                            .iter()
-                                            .position(|y| y == c)
-                                            .unwrap();
-                                        Some(TerminalColour::ORDER[x - 1])
-                                    }
-                                }
-                                None => Some(*TerminalColour::ORDER.last().unwrap()),
-                            };
+                            lce.entry.fg = cycle_colour(lce.entry.fg, false, slots);
                         }
                         1 => {
-                            lce.entry.bg = match &lce.entry.bg {
-                                Some(c) => {
-                                    if c == TerminalColour::ORDER.first().unwrap() {
-                                        None
-                                    } else {
-                                        let x = TerminalColour::ORDER
-                                            .iter()
-                                            .position(|y| y == c)
-                                            .unwrap();
-                                        Some(TerminalColour::ORDER[x - 1])
-                                    }
-                                }
-                                None => Some(*TerminalColour::ORDER.last().unwrap()),
-                            };
-                        }
-                        2 => {
-                            lce.entry.effects =
-                                if lce.entry.effects == *Effects::ORDER.first().unwrap() {
-                                    *Effects::ORDER.last().unwrap()
-                                } else {
-                                    let x = Effects::ORDER
-                                        .iter()
-                                        .position(|y| *y == lce.entry.effects)
-                                        .unwrap();
-
-                                    Effects::ORDER[x - 1]
-                                }
+                            lce.entry.bg = cycle_colour(lce.entry.bg, false, slots);
                         }
-                        _ => {}
+                        i => lce.entry.effects.toggle(Effect::ORDER[i as usize - 2]),
                     }
                     let active_scheme = &mut app
                         .savefile
@@ -252,54 +554,16 @@ pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
         KeyCode::Right => {
             if let DialogState::EditingColour(i) = app.dialog_state {
                 if app.active_lce.clone().is_some() {
+                    let slots = active_slots(app).len();
                     let mut lce = app.active_lce.clone().unwrap();
                     match i {
                         0 => {
-                            lce.entry.fg = match &lce.entry.fg {
-                                Some(c) => {
-                                    if c == TerminalColour::ORDER.last().unwrap() {
-                                        None
-                                    } else {
-                                        let x = TerminalColour::ORDER
-                                            .iter()
-                                            .position(|y| y == c)
-                                            .unwrap();
-                                        Some(TerminalColour::ORDER[x + 1])
-                                    }
-                                }
-                                None => Some(*TerminalColour::ORDER.first().unwrap()),
-                            };
+                            lce.entry.fg = cycle_colour(lce.entry.fg, true, slots);
                         }
                         1 => {
-                            lce.entry.bg = match &lce.entry.bg {
-                                Some(c) => {
-                                    if c == TerminalColour::ORDER.last().unwrap() {
-                                        None
-                                    } else {
-                                        let x = TerminalColour::ORDER
-                                            .iter()
-                                            .position(|y| y == c)
-                                            .unwrap();
-                                        Some(TerminalColour::ORDER[x + 1])
-                                    }
-                                }
-                                None => Some(*TerminalColour::ORDER.first().unwrap()),
-                            };
+                            lce.entry.bg = cycle_colour(lce.entry.bg, true, slots);
                         }
-                        2 => {
-                            lce.entry.effects =
-                                if lce.entry.effects == *Effects::ORDER.last().unwrap() {
-                                    *Effects::ORDER.first().unwrap()
-                                } else {
-                                    let x = Effects::ORDER
-                                        .iter()
-                                        .position(|y| *y == lce.entry.effects)
-                                        .unwrap();
-
-                                    Effects::ORDER[x + 1]
-                                }
-                        }
-                        _ => {}
+                        i => lce.entry.effects.toggle(Effect::ORDER[i as usize - 2]),
                     }
                     let active_scheme = &mut app
                         .savefile
@@ -313,33 +577,214 @@ pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
                 }
             }
         }
+        KeyCode::Tab if matches!(app.dialog_state, DialogState::NewExtension(..)) => {
+            if let DialogState::NewExtension(s, kind) = &app.dialog_state {
+                app.dialog_state = DialogState::NewExtension(
+                    s.clone(),
+                    match kind {
+                        PatternKind::Extension => PatternKind::Glob,
+                        PatternKind::Glob => PatternKind::Extension,
+                    },
+                )
+            }
+        }
+        KeyCode::Tab if matches!(app.dialog_state, DialogState::Export) => {
+            app.shell = app.shell.next()
+        }
+        KeyCode::Tab if matches!(app.dialog_state, DialogState::EditingSlots(_)) => {
+            edit_slot(app, |c| switch_kind(Some(c), &[]).unwrap_or(c))
+        }
+        KeyCode::Tab => {
+            let slots = active_slots(app);
+            edit_colour(app, |c| switch_kind(c, &slots))
+        }
+        KeyCode::PageUp => edit_colour(app, |c| match c {
+            Some(TerminalColour::Indexed(n)) => Some(TerminalColour::Indexed(n.wrapping_sub(16))),
+            c => c,
+        }),
+        KeyCode::PageDown => edit_colour(app, |c| match c {
+            Some(TerminalColour::Indexed(n)) => Some(TerminalColour::Indexed(n.wrapping_add(16))),
+            c => c,
+        }),
         _ => {}
     }
 }
 
-fn update_lce(lce: &ListColourEntry, scheme: &mut LsColours) {
-    match lce.text.as_str() {
-        "Normal" => scheme.norm = lce.entry,
-        "File" => scheme.file = lce.entry,
-        "Directory" => scheme.dir = lce.entry,
-        "Symlink" => scheme.link = lce.entry,
-        "Pipe" => scheme.pipe = lce.entry,
-        "Door" => scheme.door = lce.entry,
-        "Block" => scheme.block = lce.entry,
-        "Character" => scheme.char = lce.entry,
-        "Orphaned Symlink" => scheme.orphan = lce.entry,
-        "Socket" => scheme.sock = lce.entry,
-        "SetUID" => scheme.setuid = lce.entry,
-        "SetGID" => scheme.setgid = lce.entry,
-        "Sticky Other Writable" => scheme.sticky_other_writable = lce.entry,
-        "Other Writable" => scheme.other_writable = lce.entry,
-        "Sticky" => scheme.sticky = lce.entry,
-        "Executable" => scheme.exec = lce.entry,
-        "Missing" => scheme.missing = lce.entry,
-        "End Code" => scheme.rs = lce.entry,
-        _ => match scheme.extensions.iter_mut().find(|x| x.0 == lce.text) {
-            Some(x) => *x = (x.0.clone(), lce.entry),
-            None => {}
+/// Steps a colour through the named colours and back to none, through the 256 colour palette if
+/// it is indexed, or through the scheme's slots if it refers to one. Truecolor values snap back to
+/// the nearest named colour
+fn cycle_colour(
+    colour: Option<TerminalColour>,
+    forwards: bool,
+    slots: usize,
+) -> Option<TerminalColour> {
+    match colour {
+        Some(TerminalColour::Slot(n)) => {
+            let slots = slots.max(1);
+            Some(TerminalColour::Slot(match forwards {
+                true => (n as usize + 1) % slots,
+                false => (n as usize + slots - 1) % slots,
+            } as u8))
+        }
+        Some(c @ TerminalColour::Rgb(..)) => Some(c.nearest_named()),
+        Some(TerminalColour::Indexed(n)) => Some(TerminalColour::Indexed(match forwards {
+            true => n.wrapping_add(1),
+            false => n.wrapping_sub(1),
+        })),
+        Some(c) => {
+            let x = TerminalColour::ORDER.iter().position(|y| *y == c).unwrap();
+            match forwards {
+                true => TerminalColour::ORDER.get(x + 1).copied(),
+                false => x.checked_sub(1).map(|x| TerminalColour::ORDER[x]),
+            }
+        }
+        None => match forwards {
+            true => TerminalColour::ORDER.first().copied(),
+            false => TerminalColour::ORDER.last().copied(),
         },
     }
 }
+
+/// Moves a colour on to the next kind: named, then indexed, then truecolor, then the first slot if
+/// there are any, and back to named
+fn switch_kind(
+    colour: Option<TerminalColour>,
+    slots: &[(String, TerminalColour)],
+) -> Option<TerminalColour> {
+    match colour {
+        Some(c @ TerminalColour::Indexed(_)) => {
+            let (r, g, b) = c.to_rgb();
+            Some(TerminalColour::Rgb(r, g, b))
+        }
+        Some(TerminalColour::Rgb(..)) if !slots.is_empty() => Some(TerminalColour::Slot(0)),
+        Some(c @ TerminalColour::Rgb(..)) => Some(c.nearest_named()),
+        Some(TerminalColour::Slot(n)) => Some(
+            slots
+                .get(n as usize)
+                .map(|x| x.1)
+                .unwrap_or_default()
+                .nearest_named(),
+        ),
+        Some(c) => c.palette_index().map(TerminalColour::Indexed),
+        None => Some(TerminalColour::Indexed(0)),
+    }
+}
+
+/// Slots of the open scheme
+fn active_slots(app: &App) -> Vec<(String, TerminalColour)> {
+    app.open_scheme
+        .as_ref()
+        .and_then(|name| app.savefile.schemes.iter().find(|x| &x.0 == name))
+        .map(|x| x.1.slots.clone())
+        .unwrap_or_default()
+}
+
+/// Applies `edit` to the foreground or background colour selected in the colour editor
+fn edit_colour(app: &mut App, edit: impl Fn(Option<TerminalColour>) -> Option<TerminalColour>) {
+    if let DialogState::EditingColour(i) = app.dialog_state {
+        if let Some(mut lce) = app.active_lce.clone() {
+            match i {
+                0 => lce.entry.fg = edit(lce.entry.fg),
+                1 => lce.entry.bg = edit(lce.entry.bg),
+                _ => return,
+            }
+            let active_scheme = &mut app
+                .savefile
+                .schemes
+                .iter_mut()
+                .find(|x| &x.0 == app.open_scheme.as_ref().unwrap())
+                .unwrap()
+                .1;
+            update_lce(&lce, active_scheme);
+            app.active_lce = Some(lce);
+        }
+    }
+}
+
+/// Applies `edit` to the terminal background of the open scheme, which may be inherited
+fn edit_background(app: &mut App, edit: impl Fn(TerminalColour) -> TerminalColour) {
+    if let Some(background) = app.resolved_scheme().map(|x| x.background) {
+        let name = app.open_scheme.as_ref().unwrap();
+        if let Some(scheme) = app.savefile.schemes.iter_mut().find(|x| &x.0 == name) {
+            scheme.1.background = edit(background);
+            scheme.1.set_override("background");
+        }
+    }
+}
+
+/// Turns the eza section of the open scheme off, or back on. Its colours are kept while it is off,
+/// and it starts with eza's own colours if neither it nor its parent has any
+fn toggle_eza(app: &mut App) {
+    let resolved = app.resolved_scheme();
+    let shown = resolved.as_ref().is_some_and(|x| x.eza_section().is_some());
+    let colours = resolved.is_some_and(|x| x.eza.is_some());
+    if let Some(name) = &app.open_scheme {
+        if let Some(scheme) = app.savefile.schemes.iter_mut().find(|x| &x.0 == name) {
+            scheme.1.eza_disabled = shown;
+            if !shown && !colours {
+                scheme.1.eza = Some(eza::defaults());
+            }
+            app.savefile.save().unwrap();
+        }
+    }
+}
+
+/// Applies `edit` to the colour of the slot selected in the palette editor
+fn edit_slot(app: &mut App, edit: impl Fn(TerminalColour) -> TerminalColour) {
+    if let (DialogState::EditingSlots(i), Some(name)) = (&app.dialog_state, &app.open_scheme) {
+        if let Some(scheme) = app.savefile.schemes.iter_mut().find(|x| &x.0 == name) {
+            if let Some(slot) = scheme.1.slots.get_mut(*i as usize) {
+                slot.1 = edit(slot.1);
+            }
+        }
+    }
+}
+
+fn update_lce(lce: &ListColourEntry, scheme: &mut LsColours) {
+    match &lce.kind {
+        EntryKind::Builtin(key) => {
+            if let Some(x) = scheme.builtins_mut().into_iter().find(|x| x.0 == *key) {
+                *x.1 = lce.entry;
+            }
+            // A colour of its own replaces taking the colour of the link's target
+            if *key == "ln" {
+                scheme.link_target = false;
+            }
+            scheme.set_override(key);
+        }
+        EntryKind::Escape(key, code) => {
+            if let Some(x) = scheme.escapes_mut().into_iter().find(|x| x.0 == *key) {
+                *x.1 = code.clone();
+            }
+            scheme.set_override(key);
+        }
+        // Rows inherited from a parent scheme aren't stored in this one until they're changed
+        EntryKind::Extension(pattern) => {
+            match scheme.extensions.iter_mut().find(|x| &x.0 == pattern) {
+                Some(x) => x.1 = lce.entry,
+                None => scheme.extensions.push((pattern.clone(), lce.entry)),
+            }
+        }
+        EntryKind::Group(name) | EntryKind::GroupMember(name, _) => {
+            match scheme.groups.iter_mut().find(|x| &x.name == name) {
+                Some(x) => {
+                    x.entry = lce.entry;
+                    x.inherit_entry = false;
+                }
+                None => scheme.groups.push(ExtensionGroup {
+                    name: name.clone(),
+                    entry: lce.entry,
+                    patterns: vec![],
+                    inherit_entry: false,
+                }),
+            }
+        }
+        EntryKind::Eza(key) => {
+            let eza = scheme.eza.get_or_insert_with(Vec::new);
+            match eza.iter_mut().find(|x| &x.0 == key) {
+                Some(x) => x.1 = lce.entry,
+                None => eza.push((key.to_string(), lce.entry)),
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::colours::{ColourEntry, LsColours};
+
+/// Entries closer than this, by CIEDE2000, are reported as indistinguishable
+pub const MIN_DISTANCE: f64 = 10.0;
+
+/// Two file types that can't be told apart in a listing
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Clash {
+    /// `LS_COLORS` key of the first entry
+    pub first: &'static str,
+    pub first_name: &'static str,
+    /// `LS_COLORS` key of the second entry
+    pub second: &'static str,
+    pub second_name: &'static str,
+    /// CIEDE2000 difference of whichever of the foreground and background differ most
+    pub distance: f64,
+}
+
+/// Report of the `lint` command, in the shape printed as JSON
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Report {
+    pub scheme: String,
+    pub min_distance: f64,
+    pub clashes: Vec<Clash>,
+}
+
+impl Report {
+    /// What the `lint` command exits with, which is 1 if anything clashes
+    pub fn exit_code(&self) -> i32 {
+        match self.clashes.is_empty() {
+            true => 0,
+            false => 1,
+        }
+    }
+}
+
+/// The file types a listing has to keep apart, with their keys and the names the TUI uses
+fn file_types(colours: &LsColours) -> [(&'static str, &'static str, ColourEntry); 16] {
+    [
+        ("fi", "File", colours.file),
+        ("di", "Directory", colours.dir),
+        ("ln", "Symlink", colours.link),
+        ("pi", "Pipe", colours.pipe),
+        ("do", "Door", colours.door),
+        ("bd", "Block", colours.block),
+        ("cd", "Character", colours.char),
+        ("or", "Orphaned Symlink", colours.orphan),
+        ("so", "Socket", colours.sock),
+        ("su", "SetUID", colours.setuid),
+        ("sg", "SetGID", colours.setgid),
+        ("tw", "Sticky Other Writable", colours.sticky_other_writable),
+        ("ow", "Other Writable", colours.other_writable),
+        ("st", "Sticky", colours.sticky),
+        ("ex", "Executable", colours.exec),
+        ("mi", "Missing", colours.missing),
+    ]
+}
+
+/// Finds every pair of file types whose colours are too close to tell apart. Entries with
+/// different effects are always told apart, since bold or underlined text stands out on its own
+pub fn lint(colours: &LsColours) -> Vec<Clash> {
+    let entries = file_types(colours);
+    let mut clashes = vec![];
+
+    for (i, (first, first_name, a)) in entries.iter().enumerate() {
+        for (second, second_name, b) in &entries[i + 1..] {
+            if a.effects != b.effects {
+                continue;
+            }
+
+            let (a_fg, a_bg) = colours.resolve(a);
+            let (b_fg, b_bg) = colours.resolve(b);
+            let distance = a_fg.distance(&b_fg).max(a_bg.distance(&b_bg));
+
+            if distance < MIN_DISTANCE {
+                clashes.push(Clash {
+                    first,
+                    first_name,
+                    second,
+                    second_name,
+                    distance,
+                });
+            }
+        }
+    }
+
+    clashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colours::{Effect, Effects, TerminalColour};
+
+    /// A scheme where every entry has different effects, so nothing clashes until two are given
+    /// the same ones
+    fn distinct() -> LsColours {
+        let mut colours = LsColours::uncoloured();
+        for (i, (_, entry)) in colours.builtins_mut().into_iter().enumerate() {
+            for (bit, effect) in Effect::ORDER.into_iter().enumerate() {
+                if (i + 1) & (1 << bit) != 0 {
+                    entry.effects.insert(effect);
+                }
+            }
+        }
+        colours
+    }
+
+    fn report(colours: &LsColours) -> Report {
+        Report {
+            scheme: "test".to_string(),
+            min_distance: MIN_DISTANCE,
+            clashes: lint(colours),
+        }
+    }
+
+    #[test]
+    fn near_identical_colours() {
+        let mut colours = distinct();
+        colours.dir = ColourEntry::new(Effects::NONE, Some(TerminalColour::Rgb(0, 0, 255)), None);
+        colours.pipe = ColourEntry::new(Effects::NONE, Some(TerminalColour::Rgb(0, 0, 245)), None);
+
+        let report = report(&colours);
+        assert_eq!(report.clashes.len(), 1);
+        assert_eq!(
+            (report.clashes[0].first, report.clashes[0].second),
+            ("di", "pi")
+        );
+        assert!(report.clashes[0].distance < MIN_DISTANCE);
+        assert_eq!(report.exit_code(), 1);
+    }
+
+    #[test]
+    fn distinct_colours() {
+        let mut colours = distinct();
+        colours.dir = ColourEntry::new(Effects::NONE, Some(TerminalColour::Blue), None);
+        colours.pipe = ColourEntry::new(Effects::NONE, Some(TerminalColour::Orange), None);
+
+        let report = report(&colours);
+        assert!(report.clashes.is_empty());
+        assert_eq!(report.exit_code(), 0);
+    }
+}
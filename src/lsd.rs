@@ -0,0 +1,296 @@
+use crate::colours::{ColourEntry, Effects, LsColours, TerminalColour};
+
+/// Keys of the `file-type` section of an lsd `colors.yaml` theme, as a path through its nested
+/// tables, with the `LS_COLORS` key of the entry that holds the same colour. Where several keys
+/// share an entry, the first is the one read on import
+const FILE_TYPES: [(&str, &str); 14] = [
+    ("file-type.file.exec-uid", "ex"),
+    ("file-type.file.uid-no-exec", "fi"),
+    ("file-type.file.exec-no-uid", "ex"),
+    ("file-type.file.no-exec-no-uid", "fi"),
+    ("file-type.dir.uid", "di"),
+    ("file-type.dir.no-uid", "di"),
+    ("file-type.pipe", "pi"),
+    ("file-type.symlink.default", "ln"),
+    ("file-type.symlink.broken", "or"),
+    ("file-type.symlink.missing-target", "mi"),
+    ("file-type.block-device", "bd"),
+    ("file-type.char-device", "cd"),
+    ("file-type.socket", "so"),
+    ("file-type.special", "do"),
+];
+
+/// The rest of the keys of a theme, with the `EZA_COLORS` key of the eza section that holds the
+/// same colour. Where several keys share an eza key, the first is the one read on import
+const KEYS: [(&str, &str); 27] = [
+    ("user", "uu"),
+    ("group", "gu"),
+    ("permission.read", "ur"),
+    ("permission.write", "uw"),
+    ("permission.exec", "ux"),
+    ("permission.exec-sticky", "su"),
+    ("permission.no-access", "xx"),
+    ("permission.acl", "xa"),
+    ("date.hour-old", "da"),
+    ("date.day-old", "da"),
+    ("date.older", "da"),
+    ("size.none", "xx"),
+    ("size.small", "sn"),
+    ("size.medium", "sn"),
+    ("size.large", "sn"),
+    ("inode.valid", "in"),
+    ("inode.invalid", "xx"),
+    ("links.valid", "lc"),
+    ("links.invalid", "xx"),
+    ("tree-edge", "xx"),
+    ("git-status.default", "xx"),
+    ("git-status.new-in-index", "ga"),
+    ("git-status.new-in-workdir", "ga"),
+    ("git-status.typechange", "gt"),
+    ("git-status.deleted", "gd"),
+    ("git-status.renamed", "gv"),
+    ("git-status.modified", "gm"),
+];
+
+/// lsd's names for the 16 ANSI colours, in palette order
+const NAMES: [&str; 16] = [
+    "black",
+    "dark_red",
+    "dark_green",
+    "dark_yellow",
+    "dark_blue",
+    "dark_magenta",
+    "dark_cyan",
+    "grey",
+    "dark_grey",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+];
+
+/// Converts a scheme to an lsd `colors.yaml` theme, along with a warning for everything that had
+/// to be left out. The `file-type` section comes from the kinds of file the scheme colours, and
+/// the rest from its eza section if it has one. Entries without a foreground are left out, so lsd
+/// keeps its own colours for them
+pub fn export(colours: &LsColours) -> (String, Vec<String>) {
+    let mut colours = colours.with_slots_resolved();
+    let mut warnings = vec![];
+
+    let link_target = colours.link_target;
+    let mut entries = vec![];
+    let builtins = colours.builtins_mut();
+    for (path, key) in FILE_TYPES {
+        if key == "ln" && link_target {
+            continue;
+        }
+        let entry = *builtins.iter().find(|x| x.0 == key).unwrap().1;
+        entries.push((path, key, entry));
+    }
+    if let Some(eza) = colours.eza_section() {
+        for (path, key) in KEYS {
+            if let Some(x) = eza.iter().find(|x| x.0 == key) {
+                entries.push((path, key, x.1));
+            }
+        }
+    }
+
+    let mut value = String::from("# lsd colour theme, generated by lscoltui\n");
+    let mut previous: Vec<&str> = vec![];
+    let mut warned: Vec<&str> = vec![];
+    for (path, key, entry) in entries {
+        let fg = match entry.fg {
+            Some(fg) => fg,
+            None => continue,
+        };
+
+        // Each path is written under the tables it doesn't share with the one before
+        let parts = path.split('.').collect::<Vec<_>>();
+        let (tables, name) = parts.split_at(parts.len() - 1);
+        let shared = previous
+            .iter()
+            .zip(tables)
+            .take_while(|(a, b)| a == b)
+            .count();
+        for (depth, table) in tables.iter().enumerate().skip(shared) {
+            value.push_str(&format!("{}{}:\n", "  ".repeat(depth), table));
+        }
+        value.push_str(&format!(
+            "{}{}: {}\n",
+            "  ".repeat(tables.len()),
+            name[0],
+            colour_name(fg)
+        ));
+        previous = tables.to_vec();
+
+        if (entry.bg.is_some() || entry.effects != Effects::NONE) && !warned.contains(&key) {
+            warned.push(key);
+            warnings.push(format!(
+                "{}: Backgrounds and effects have no equivalent, so they are left out",
+                path
+            ));
+        }
+    }
+
+    if link_target {
+        warnings.push(
+            "file-type.symlink.default: Colouring links like their target has no equivalent, so \
+             lsd's own colour is used"
+                .to_string(),
+        );
+    }
+    if colours.eza_section().is_none() {
+        warnings
+            .push("No eza section, so lsd keeps its own colours apart from file types".to_string());
+    }
+
+    (value, warnings)
+}
+
+/// Reads an lsd `colors.yaml` theme. The `file-type` section sets the kinds of file, leaving
+/// those it doesn't cover uncoloured, and everything else goes in the eza section
+pub fn parse(contents: &str) -> Result<LsColours, String> {
+    let mut colours = LsColours::uncoloured();
+    let mut builtins: Vec<&str> = vec![];
+    let mut eza: Vec<(String, ColourEntry)> = vec![];
+    // Tables the current line is nested in, with their indentation
+    let mut tables: Vec<(usize, &str)> = vec![];
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match trimmed.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+
+        let indent = line.len() - line.trim_start().len();
+        while tables.last().is_some_and(|x| x.0 >= indent) {
+            tables.pop();
+        }
+        if value.is_empty() || value.starts_with('#') {
+            tables.push((indent, key));
+            continue;
+        }
+
+        let path = tables
+            .iter()
+            .map(|x| x.1)
+            .chain([key])
+            .collect::<Vec<_>>()
+            .join(".");
+        let entry = || {
+            colour(value)
+                .map(|fg| ColourEntry::new(Effects::NONE, Some(fg), None))
+                .ok_or(format!("Invalid colour \'{}\' for {}", value, path))
+        };
+
+        if let Some((_, key)) = FILE_TYPES.iter().find(|x| x.0 == path) {
+            if !builtins.contains(key) {
+                let entry = entry()?;
+                builtins.push(key);
+                if let Some(x) = colours.builtins_mut().into_iter().find(|x| x.0 == *key) {
+                    *x.1 = entry;
+                }
+            }
+        } else if let Some((_, key)) = KEYS.iter().find(|x| x.0 == path) {
+            if !eza.iter().any(|x| x.0 == *key) {
+                eza.push((key.to_string(), entry()?));
+            }
+        }
+    }
+
+    if builtins.is_empty() && eza.is_empty() {
+        return Err("No lsd theme colours found".to_string());
+    }
+    if !eza.is_empty() {
+        colours.eza = Some(eza);
+    }
+
+    Ok(colours)
+}
+
+/// How a colour is written in a theme: a name for the ANSI colours, a number for the rest of the
+/// 256 colour palette and hex for truecolor
+fn colour_name(colour: TerminalColour) -> String {
+    match colour.palette_index() {
+        Some(n @ 0..=15) => NAMES[n as usize].to_string(),
+        Some(n) => n.to_string(),
+        None => format!("\"{}\"", colour.to_hex()),
+    }
+}
+
+/// Parses a theme colour, optionally in quotes and followed by a comment
+fn colour(value: &str) -> Option<TerminalColour> {
+    let value = match value.strip_prefix(['\'', '"']) {
+        Some(quoted) => quoted.split(['\'', '"']).next().unwrap_or_default(),
+        None => value.split(" #").next().unwrap_or_default().trim(),
+    };
+
+    if let Some(n) = NAMES
+        .iter()
+        .position(|x| *x == value.to_ascii_lowercase().replace("gray", "grey"))
+    {
+        return Some(TerminalColour::ANSI[n]);
+    }
+    match value.parse::<u8>() {
+        Ok(n @ 0..=15) => Some(TerminalColour::ANSI[n as usize]),
+        Ok(n) => Some(TerminalColour::Indexed(n)),
+        Err(_) => TerminalColour::from_hex(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THEME: &str = "\
+user: 230
+permission:
+  read: dark_green
+  exec-sticky: 5 # sticky
+file-type:
+  file:
+    exec-uid: 40
+  dir:
+    uid: \"#0087ff\"
+  symlink:
+    default: Cyan
+";
+
+    #[test]
+    fn parse_theme() {
+        let colours = parse(THEME).unwrap();
+        assert_eq!(colours.dir.fg, Some(TerminalColour::Rgb(0, 135, 255)));
+        assert_eq!(colours.exec.fg, Some(TerminalColour::Indexed(40)));
+        assert_eq!(colours.link.fg, Some(TerminalColour::Turquoise));
+        assert_eq!(colours.file, ColourEntry::default());
+        assert_eq!(colours.norm, ColourEntry::default());
+
+        let eza = colours.eza.unwrap();
+        let fg = |key: &str| eza.iter().find(|x| x.0 == key).and_then(|x| x.1.fg);
+        assert_eq!(fg("uu"), Some(TerminalColour::Indexed(230)));
+        assert_eq!(fg("ur"), Some(TerminalColour::Green));
+        assert_eq!(fg("su"), Some(TerminalColour::Purple));
+    }
+
+    #[test]
+    fn round_trip() {
+        let colours = parse(THEME).unwrap();
+        let (theme, _) = export(&colours);
+        assert_eq!(parse(&theme), Ok(colours));
+    }
+
+    #[test]
+    fn invalid_colour() {
+        assert_eq!(
+            parse("file-type:\n  pipe: nope\n"),
+            Err("Invalid colour 'nope' for file-type.pipe".to_string())
+        );
+    }
+}
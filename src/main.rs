@@ -1,16 +1,28 @@
+pub mod bsd;
+pub mod check;
 pub mod colours;
+pub mod dircolors;
+pub mod eza;
 pub mod file;
 pub mod key_events;
+pub mod lint;
+pub mod lsd;
+pub mod palette;
+pub mod presets;
 pub mod ui;
+pub mod variant;
+pub mod wallpaper;
 
 use std::{
     io::stdout,
     panic::{set_hook, take_hook},
+    path::PathBuf,
 };
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use colours::LsColours;
 use crossterm::{execute, terminal::disable_raw_mode};
-use file::SaveFile;
+use file::{SaveFile, Shell};
 use ui::App;
 
 #[derive(Parser)]
@@ -24,37 +36,395 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     #[command(about = "Prints the command to set the colours. Recommended to chain with eval $()")]
-    Export { name: Option<String> },
+    Export {
+        name: Option<String>,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Env)]
+        format: ExportFormat,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = Shell::Bash,
+            help = "Shell the command is written for, with --format env, bsd or eza"
+        )]
+        shell: Shell,
+    },
+    #[command(about = "Saves the colours currently in $LS_COLORS as a new scheme")]
+    ImportEnv { name: String },
+    #[command(about = "Creates a new scheme from a colour configuration file")]
+    Import {
+        #[arg(long, value_enum, required_unless_present_any = ["from_pywal", "from_image"])]
+        format: Option<ImportFormat>,
+        #[arg(required_unless_present_any = ["from_pywal", "from_image"])]
+        file: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["format", "file"],
+            help = "Generates the scheme from pywal's colours, by default in ~/.cache/wal/colors.json. Running it again updates the scheme"
+        )]
+        from_pywal: Option<Option<PathBuf>>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = ["format", "file", "from_pywal"],
+            help = "Generates a truecolor scheme from the main colours of a PNG or JPEG image, such as a wallpaper"
+        )]
+        from_image: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Name of the new scheme. Defaults to the file name, or 'pywal' with --from-pywal"
+        )]
+        name: Option<String>,
+    },
+    #[command(
+        about = "Reports file types whose colours are too similar to tell apart. Exits with 1 if any are found"
+    )]
+    Lint {
+        name: Option<String>,
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+    },
+    #[command(
+        about = "Sets the terminal palette a scheme is previewed and checked with, from a base16, Alacritty, kitty or Xresources theme"
+    )]
+    Palette {
+        name: String,
+        file: Option<PathBuf>,
+        #[arg(
+            long,
+            conflicts_with = "file",
+            help = "Go back to xterm's default colours"
+        )]
+        clear: bool,
+    },
+    #[command(
+        about = "Reports invalid, duplicated and shadowed patterns. Exits with 1 if any are found"
+    )]
+    Check {
+        name: Option<String>,
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+    },
+    #[command(
+        about = "Makes a scheme build on another, keeping only the entries it changes. Creates the scheme if it doesn't exist"
+    )]
+    Inherit {
+        name: String,
+        parent: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "parent",
+            help = "Stop inheriting, keeping a copy of the inherited colours"
+        )]
+        clear: bool,
+    },
+    #[command(
+        about = "Creates a variant of a scheme for the opposite terminal background, which follows changes to the scheme"
+    )]
+    Variant {
+        source: String,
+        #[arg(
+            long,
+            help = "Name of the variant. Defaults to the scheme's name followed by -light or -dark"
+        )]
+        name: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    #[value(help = "A shell command setting $LS_COLORS")]
+    Env,
+    #[value(help = "A dircolors database, for use with dircolors -b")]
+    Dircolors,
+    #[value(
+        help = "A shell command setting $LSCOLORS for BSD and macOS ls, which also need $CLICOLOR set"
+    )]
+    Bsd,
+    #[value(help = "Shell commands setting $EZA_COLORS, and $EXA_COLORS for exa")]
+    Eza,
+    #[value(help = "An lsd colors.yaml theme, with file types and any eza section")]
+    Lsd,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportFormat {
+    #[value(help = "A dircolors database, such as ~/.dircolors")]
+    Dircolors,
+    #[value(help = "A file holding a BSD LSCOLORS string, or a line setting it")]
+    Bsd,
+    #[value(help = "An lsd colors.yaml theme, with its non-file colours kept as an eza section")]
+    Lsd,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    #[value(help = "One line per problem")]
+    Text,
+    #[value(help = "A JSON object listing the problems")]
+    Json,
 }
 
 fn main() -> std::io::Result<()> {
     let cli = Cli::parse();
     let savefile = SaveFile::load();
 
-    if cli.command.is_some() {
-        match &cli.command.unwrap() {
-            Commands::Export { name } => match name {
+    if let Some(command) = &cli.command {
+        match command {
+            Commands::Export {
+                name,
+                format,
+                shell,
+            } => match name {
                 Some(s) => {
-                    match savefile.schemes.iter().find(|x| &x.0 == s) {
-                        Some(scheme) => println!("{}", file::env_command(&scheme.1)),
-                        None => {
-                            eprintln!("Unable to find scheme \'{}\'", s)
-                        }
+                    match savefile.resolve(s) {
+                        Ok(colours) => println!("{}", export(&colours, *format, *shell)),
+                        Err(e) => eprintln!("{}", e),
                     }
                     return Ok(());
                 }
                 None => {
-                    match savefile
-                        .schemes
-                        .iter()
-                        .find(|x| x.0 == savefile.most_recent)
-                    {
-                        Some(scheme) => println!("{}", file::env_command(&scheme.1)),
-                        None => {}
+                    if savefile.schemes.iter().any(|x| x.0 == savefile.most_recent) {
+                        match savefile.resolve(&savefile.most_recent) {
+                            Ok(colours) => println!("{}", export(&colours, *format, *shell)),
+                            Err(e) => eprintln!("{}", e),
+                        }
                     }
                     return Ok(());
                 }
             },
+            Commands::ImportEnv { name } => {
+                return import_scheme(savefile, name, "$LS_COLORS", file::env_colours());
+            }
+            Commands::Import {
+                from_pywal: Some(path),
+                name,
+                ..
+            } => {
+                let path = path.clone().unwrap_or_else(|| {
+                    dirs::cache_dir()
+                        .unwrap_or_default()
+                        .join("wal")
+                        .join("colors.json")
+                });
+                return import_pywal(savefile, name.as_deref().unwrap_or("pywal"), &path);
+            }
+            Commands::Import {
+                from_image: Some(path),
+                name,
+                ..
+            } => {
+                let name = name.clone().unwrap_or_else(|| file_name(path));
+                let colours = wallpaper::scheme(path);
+                return import_scheme(savefile, &name, &path.to_string_lossy(), colours);
+            }
+            Commands::Import {
+                format: Some(format),
+                file: Some(file),
+                name,
+                ..
+            } => {
+                let name = name.clone().unwrap_or_else(|| file_name(file));
+                let colours = std::fs::read_to_string(file)
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| match format {
+                        ImportFormat::Dircolors => dircolors::parse(&contents),
+                        ImportFormat::Bsd => bsd::parse(&contents),
+                        ImportFormat::Lsd => lsd::parse(&contents),
+                    });
+                return import_scheme(savefile, &name, &file.to_string_lossy(), colours);
+            }
+            Commands::Import { .. } => unreachable!("clap requires a format and file"),
+            Commands::Lint { name, format } => {
+                let (name, scheme) = report_scheme(&savefile, name);
+                let report = lint::Report {
+                    scheme: name.to_string(),
+                    min_distance: lint::MIN_DISTANCE,
+                    clashes: lint::lint(&scheme),
+                };
+                match format {
+                    ReportFormat::Text => {
+                        for clash in &report.clashes {
+                            println!(
+                                "{} ({}) and {} ({}) look alike, with a difference of {:.2}",
+                                clash.first_name,
+                                clash.first,
+                                clash.second_name,
+                                clash.second,
+                                clash.distance
+                            );
+                        }
+                    }
+                    ReportFormat::Json => {
+                        println!("{}", serde_json::to_string_pretty(&report).unwrap())
+                    }
+                }
+
+                match report.exit_code() {
+                    0 => return Ok(()),
+                    code => std::process::exit(code),
+                }
+            }
+            Commands::Palette { name, file, clear } => {
+                let mut savefile = savefile;
+                let scheme = match savefile.schemes.iter_mut().find(|x| &x.0 == name) {
+                    Some(scheme) => &mut scheme.1,
+                    None => {
+                        eprintln!("Unable to find scheme \'{}\'", name);
+                        return Ok(());
+                    }
+                };
+
+                if *clear {
+                    scheme.palette = None;
+                    savefile.save()?;
+                    println!("Cleared the palette of \'{}\'", name);
+                } else if let Some(file) = file {
+                    match palette::load(file) {
+                        Ok(palette) => {
+                            if let Some(background) = palette.background {
+                                scheme.background = background;
+                                scheme.set_override("background");
+                            }
+                            scheme.palette = Some(palette);
+                            savefile.save()?;
+                            println!("Set the palette of \'{}\' from {}", name, file.display());
+                        }
+                        Err(e) => eprintln!("Unable to load {}: {}", file.display(), e),
+                    }
+                } else {
+                    match &scheme.palette {
+                        Some(palette) => println!("{}", palette.source.display()),
+                        None => println!("\'{}\' has no palette", name),
+                    }
+                }
+                return Ok(());
+            }
+            Commands::Check { name, format } => {
+                let (_, scheme) = report_scheme(&savefile, name);
+                let problems = check::check(&scheme);
+                match format {
+                    ReportFormat::Text => {
+                        for problem in &problems {
+                            println!(
+                                "{}: {}",
+                                match problem.severity {
+                                    check::Severity::Error => "error",
+                                    check::Severity::Warning => "warning",
+                                },
+                                problem.message
+                            );
+                        }
+                    }
+                    ReportFormat::Json => {
+                        println!("{}", serde_json::to_string_pretty(&problems).unwrap())
+                    }
+                }
+
+                if !problems.is_empty() {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            Commands::Inherit {
+                name,
+                parent,
+                clear,
+            } => {
+                let mut savefile = savefile;
+                let index = savefile.schemes.iter().position(|x| &x.0 == name);
+
+                if let Some(parent) = parent {
+                    let inherited = match savefile.resolve(parent) {
+                        Ok(colours) => colours,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return Ok(());
+                        }
+                    };
+
+                    match index {
+                        Some(i) => {
+                            savefile.schemes[i].1.set_parent(parent, &inherited);
+                            if let Err(e) = savefile.resolve(name) {
+                                eprintln!("{}", e);
+                                return Ok(());
+                            }
+                            savefile.save()?;
+                            println!(
+                                "\'{}\' now inherits from \'{}\', overriding {} entries",
+                                name,
+                                parent,
+                                savefile.schemes[i].1.overrides.len()
+                            );
+                        }
+                        None => {
+                            savefile.schemes.push((
+                                name.clone(),
+                                LsColours {
+                                    parent: Some(parent.clone()),
+                                    ..LsColours::default()
+                                },
+                            ));
+                            savefile.save()?;
+                            println!("Created \'{}\', inheriting from \'{}\'", name, parent);
+                        }
+                    }
+                    return Ok(());
+                }
+
+                let i = match index {
+                    Some(i) => i,
+                    None => {
+                        eprintln!("Unable to find scheme \'{}\'", name);
+                        return Ok(());
+                    }
+                };
+                match savefile.schemes[i].1.parent.clone() {
+                    Some(parent) if *clear => {
+                        savefile.detach(i);
+                        savefile.save()?;
+                        println!("\'{}\' no longer inherits from \'{}\'", name, parent);
+                    }
+                    Some(parent) => println!("{}", parent),
+                    None => println!("\'{}\' has no parent", name),
+                }
+                return Ok(());
+            }
+            Commands::Variant { source, name } => {
+                let mut savefile = savefile;
+                let colours = match savefile.resolve(source) {
+                    Ok(colours) => colours,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return Ok(());
+                    }
+                };
+                let name = name
+                    .clone()
+                    .unwrap_or_else(|| variant::name(&savefile, source, &colours));
+
+                if name.is_empty() {
+                    eprintln!("Scheme name cannot be empty");
+                } else if savefile.schemes.iter().any(|x| x.0 == name) {
+                    eprintln!("Scheme \'{}\' already exists", name);
+                } else {
+                    savefile
+                        .schemes
+                        .push((name.clone(), variant::linked(source)));
+                    savefile.save()?;
+                    println!(
+                        "Created \'{}\', a {} variant of \'{}\' that follows its changes",
+                        name,
+                        match variant::is_dark(&colours) {
+                            true => "light",
+                            false => "dark",
+                        },
+                        source
+                    );
+                }
+                return Ok(());
+            }
         }
     }
 
@@ -77,6 +447,107 @@ fn main() -> std::io::Result<()> {
     app_result
 }
 
+fn export(colours: &LsColours, format: ExportFormat, shell: Shell) -> String {
+    match format {
+        ExportFormat::Env => file::env_command(colours, shell),
+        ExportFormat::Dircolors => dircolors::export(colours),
+        ExportFormat::Bsd => {
+            let (value, warnings) = bsd::export(colours);
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
+            }
+            file::set_env_command("LSCOLORS", &value, shell)
+        }
+        ExportFormat::Eza => {
+            let value = eza::export(colours);
+            format!(
+                "{}\n{}",
+                file::set_env_command("EZA_COLORS", &value, shell),
+                file::set_env_command("EXA_COLORS", &value, shell)
+            )
+        }
+        ExportFormat::Lsd => {
+            let (value, warnings) = lsd::export(colours);
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
+            }
+            value
+        }
+    }
+}
+
+/// Finds the scheme a report command runs on, defaulting to the most recently opened one, with
+/// everything it inherits filled in. Exits with 2 if there is no such scheme, so scripts can tell
+/// it apart from a failed report
+fn report_scheme<'a>(savefile: &'a SaveFile, name: &'a Option<String>) -> (&'a str, LsColours) {
+    let name = name.as_ref().unwrap_or(&savefile.most_recent);
+    match savefile.resolve(name) {
+        Ok(colours) => (name, colours),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Name for a scheme imported from a file, without its extension or a leading '.'
+fn file_name(path: &std::path::Path) -> String {
+    path.file_stem()
+        .map(|x| x.to_string_lossy().trim_start_matches('.').to_string())
+        .unwrap_or_default()
+}
+
+/// Saves imported colours as a new scheme, refusing to replace an existing one
+fn import_scheme(
+    mut savefile: SaveFile,
+    name: &str,
+    source: &str,
+    colours: Result<LsColours, String>,
+) -> std::io::Result<()> {
+    if name.is_empty() {
+        eprintln!("Scheme name cannot be empty");
+    } else if savefile.schemes.iter().any(|x| x.0 == name) {
+        eprintln!("Scheme \'{}\' already exists", name);
+    } else {
+        match colours {
+            Ok(colours) => {
+                savefile.schemes.push((name.to_string(), colours));
+                savefile.save()?;
+                println!("Imported {} as \'{}\'", source, name);
+            }
+            Err(e) => eprintln!("Unable to import {}: {}", source, e),
+        }
+    }
+    Ok(())
+}
+
+/// Generates a scheme from pywal's colours. Unlike other imports this updates a scheme of the same
+/// name, so it can be rerun whenever the wallpaper changes. Changes made by hand are kept, apart
+/// from those to the kinds of file
+fn import_pywal(mut savefile: SaveFile, name: &str, path: &std::path::Path) -> std::io::Result<()> {
+    let palette = match palette::load_pywal(path) {
+        Ok(palette) => palette,
+        Err(e) => {
+            eprintln!("Unable to import {}: {}", path.display(), e);
+            return Ok(());
+        }
+    };
+    match savefile.schemes.iter_mut().find(|x| x.0 == name) {
+        Some(scheme) => {
+            presets::update_from_palette(&mut scheme.1, &palette);
+            savefile.save()?;
+            println!("Updated \'{}\' from {}", name, path.display());
+        }
+        None => {
+            let colours = presets::from_palette(&palette);
+            savefile.schemes.push((name.to_string(), colours));
+            savefile.save()?;
+            println!("Imported {} as \'{}\'", path.display(), name);
+        }
+    }
+    Ok(())
+}
+
 pub fn init_panic_hook() {
     println!("Quit signal recieved, app shutting down");
     let original_hook = take_hook();
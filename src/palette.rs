@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::colours::{TerminalColour, TerminalPalette};
+
+/// base16 slots for each ANSI colour, following base16-shell. The bright colours reuse the normal
+/// ones, apart from bright black and bright white
+const BASE16_SLOTS: [&str; 16] = [
+    "base00", "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base05", "base03",
+    "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base07",
+];
+
+/// Alacritty's names for the ANSI colours, used in both its `normal` and `bright` tables
+const ALACRITTY_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Reads a terminal theme, working out its format from its contents. Supports base16 schemes
+/// (YAML), Alacritty configs (TOML), kitty configs and Xresources
+pub fn load(path: &Path) -> Result<TerminalPalette, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let source = path.canonicalize().unwrap_or(path.to_path_buf());
+
+    if contents.contains("base00") {
+        base16(&contents, source)
+    } else if contents.contains("[colors") {
+        alacritty(&contents, source)
+    } else {
+        // kitty and Xresources only differ in the separator and the prefix of each key
+        colour_lines(&contents, source)
+    }
+}
+
+/// Reads the palette pywal generated from a wallpaper, usually `~/.cache/wal/colors.json`
+pub fn load_pywal(path: &Path) -> Result<TerminalPalette, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let source = path.canonicalize().unwrap_or(path.to_path_buf());
+    pywal(&contents, source)
+}
+
+/// Parses `#rrggbb`, `rrggbb` and `0xrrggbb`, optionally in quotes
+fn colour(value: &str) -> Option<TerminalColour> {
+    let value = value.trim().trim_matches(['\'', '"']);
+    TerminalColour::from_hex(value.strip_prefix("0x").unwrap_or(value))
+}
+
+/// Builds a palette from one lookup per ANSI colour, failing on the first that is missing
+fn collect(
+    source: PathBuf,
+    lookup: impl Fn(usize) -> Result<TerminalColour, String>,
+    foreground: Option<TerminalColour>,
+    background: Option<TerminalColour>,
+) -> Result<TerminalPalette, String> {
+    let mut ansi = [TerminalColour::default(); 16];
+    for (i, slot) in ansi.iter_mut().enumerate() {
+        *slot = lookup(i)?;
+    }
+
+    Ok(TerminalPalette {
+        source,
+        ansi,
+        foreground,
+        background,
+    })
+}
+
+fn base16(contents: &str, source: PathBuf) -> Result<TerminalPalette, String> {
+    // Both the original flat format and the newer one with a `palette` table keep each slot on a
+    // line of its own
+    let slot = |name: &str| {
+        contents
+            .lines()
+            .filter_map(|x| x.split_once(':'))
+            .find(|x| {
+                x.0.trim()
+                    .trim_matches(['\'', '"'])
+                    .eq_ignore_ascii_case(name)
+            })
+            .and_then(|x| colour(x.1.split_whitespace().next().unwrap_or_default()))
+    };
+
+    collect(
+        source,
+        |i| slot(BASE16_SLOTS[i]).ok_or(format!("Missing or invalid {}", BASE16_SLOTS[i])),
+        slot("base05"),
+        slot("base00"),
+    )
+}
+
+fn alacritty(contents: &str, source: PathBuf) -> Result<TerminalPalette, String> {
+    let config: toml::Table = contents.parse().map_err(|e| format!("{}", e))?;
+    let colours = config.get("colors").and_then(|x| x.as_table());
+    let get = |table: &str, name: &str| {
+        colours
+            .and_then(|x| x.get(table))
+            .and_then(|x| x.get(name))
+            .and_then(|x| x.as_str())
+            .and_then(colour)
+    };
+
+    collect(
+        source,
+        |i| {
+            let table = match i < 8 {
+                true => "normal",
+                false => "bright",
+            };
+            let name = ALACRITTY_NAMES[i % 8];
+            get(table, name).ok_or(format!("Missing or invalid colors.{}.{}", table, name))
+        },
+        get("primary", "foreground"),
+        get("primary", "background"),
+    )
+}
+
+fn colour_lines(contents: &str, source: PathBuf) -> Result<TerminalPalette, String> {
+    let mut ansi = [None; 16];
+    let mut foreground = None;
+    let mut background = None;
+
+    for line in contents.lines().map(|x| x.trim()) {
+        if line.starts_with(['#', '!']) {
+            continue;
+        }
+
+        // `color4 #0000ee` in kitty, `*.color4: #0000ee` or `URxvt.color4: #0000ee` in Xresources
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.rsplit(['.', '*']).next().unwrap_or(key), value),
+            None => match line.split_once(char::is_whitespace) {
+                Some(x) => x,
+                None => continue,
+            },
+        };
+
+        let value = match colour(value) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        match key.trim() {
+            "foreground" => foreground = Some(value),
+            "background" => background = Some(value),
+            key => {
+                if let Some(n) = key
+                    .strip_prefix("color")
+                    .and_then(|x| x.parse::<usize>().ok())
+                {
+                    if n < 16 {
+                        ansi[n] = Some(value);
+                    }
+                }
+            }
+        }
+    }
+
+    collect(
+        source,
+        |i| ansi[i].ok_or(format!("Missing or invalid color{}", i)),
+        foreground,
+        background,
+    )
+}
+
+fn pywal(contents: &str, source: PathBuf) -> Result<TerminalPalette, String> {
+    #[derive(serde::Deserialize)]
+    struct Special {
+        background: String,
+        foreground: String,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Pywal {
+        special: Special,
+        colors: HashMap<String, String>,
+    }
+
+    let pywal: Pywal = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+
+    collect(
+        source,
+        |i| {
+            pywal
+                .colors
+                .get(&format!("color{}", i))
+                .and_then(|x| colour(x))
+                .ok_or(format!("Missing or invalid color{}", i))
+        },
+        colour(&pywal.special.foreground),
+        colour(&pywal.special.background),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE16: &str = "\
+scheme: \"Test\"
+author: \"lscoltui\"
+base00: \"0080ff\"
+base01: \"1080ef\"
+base02: \"2080df\"
+base03: \"3080cf\"
+base04: \"4080bf\"
+base05: \"5080af\" # foreground
+base06: \"60809f\"
+base07: \"70808f\"
+base08: \"80807f\"
+base09: \"90806f\"
+base0A: \"a0805f\"
+base0B: \"b0804f\"
+base0C: \"c0803f\"
+base0D: \"d0802f\"
+base0E: \"e0801f\"
+base0F: \"f0800f\"
+";
+
+    const ALACRITTY: &str = "\
+[colors.primary]
+background = '#101010'
+foreground = '#e0e0e0'
+
+[colors.normal]
+black = '0x0080ff'
+red = '0x1080ef'
+green = '0x2080df'
+yellow = '0x3080cf'
+blue = '0x4080bf'
+magenta = '0x5080af'
+cyan = '0x60809f'
+white = '0x70808f'
+
+[colors.bright]
+black = '0x80807f'
+red = '0x90806f'
+green = '0xa0805f'
+yellow = '0xb0804f'
+blue = '0xc0803f'
+magenta = '0xd0802f'
+cyan = '0xe0801f'
+white = '0xf0800f'
+";
+
+    const KITTY: &str = "\
+# Test theme
+font_size 11.0
+foreground #e0e0e0
+background #101010
+cursor #ffffff
+color0 #0080ff
+color1 #1080ef
+color2 #2080df
+color3 #3080cf
+color4 #4080bf
+color5 #5080af
+color6 #60809f
+color7 #70808f
+color8 #80807f
+color9 #90806f
+color10 #a0805f
+color11 #b0804f
+color12 #c0803f
+color13 #d0802f
+color14 #e0801f
+color15 #f0800f
+";
+
+    const XRESOURCES: &str = "\
+! Test theme
+*.foreground: #e0e0e0
+*.background: #101010
+*.color0: #0080ff
+*.color1: #1080ef
+*.color2: #2080df
+*.color3: #3080cf
+*.color4: #4080bf
+*.color5: #5080af
+*.color6: #60809f
+*.color7: #70808f
+URxvt.color8: #80807f
+URxvt.color9: #90806f
+URxvt.color10: #a0805f
+URxvt.color11: #b0804f
+URxvt*color12: #c0803f
+URxvt*color13: #d0802f
+URxvt*color14: #e0801f
+URxvt*color15: #f0800f
+";
+
+    const PYWAL: &str = r##"{
+    "wallpaper": "/tmp/wallpaper.png",
+    "special": {
+        "background": "#101010",
+        "foreground": "#e0e0e0",
+        "cursor": "#ffffff"
+    },
+    "colors": {
+        "color0": "#0080ff",
+        "color1": "#1080ef",
+        "color2": "#2080df",
+        "color3": "#3080cf",
+        "color4": "#4080bf",
+        "color5": "#5080af",
+        "color6": "#60809f",
+        "color7": "#70808f",
+        "color8": "#80807f",
+        "color9": "#90806f",
+        "color10": "#a0805f",
+        "color11": "#b0804f",
+        "color12": "#c0803f",
+        "color13": "#d0802f",
+        "color14": "#e0801f",
+        "color15": "#f0800f"
+    }
+}"##;
+
+    /// The colour every fixture gives to palette entry, or base16 slot, `n`
+    fn fixture(n: usize) -> TerminalColour {
+        TerminalColour::Rgb(n as u8 * 16, 0x80, 255 - n as u8 * 16)
+    }
+
+    fn check(palette: Result<TerminalPalette, String>) {
+        let palette = palette.unwrap();
+        assert_eq!(palette.ansi, std::array::from_fn(fixture));
+        assert_eq!(
+            palette.foreground,
+            Some(TerminalColour::Rgb(0xe0, 0xe0, 0xe0))
+        );
+        assert_eq!(
+            palette.background,
+            Some(TerminalColour::Rgb(0x10, 0x10, 0x10))
+        );
+    }
+
+    #[test]
+    fn base16_scheme() {
+        let palette = base16(BASE16, PathBuf::new()).unwrap();
+        for (i, slot) in BASE16_SLOTS.iter().enumerate() {
+            let n = usize::from_str_radix(&slot[4..], 16).unwrap();
+            assert_eq!(palette.ansi[i], fixture(n), "colour {} from {}", i, slot);
+        }
+        assert_eq!(palette.foreground, Some(fixture(5)));
+        assert_eq!(palette.background, Some(fixture(0)));
+    }
+
+    #[test]
+    fn alacritty_config() {
+        check(alacritty(ALACRITTY, PathBuf::new()));
+    }
+
+    #[test]
+    fn kitty_config() {
+        check(colour_lines(KITTY, PathBuf::new()));
+    }
+
+    #[test]
+    fn xresources() {
+        check(colour_lines(XRESOURCES, PathBuf::new()));
+    }
+
+    #[test]
+    fn pywal_colours() {
+        check(pywal(PYWAL, PathBuf::new()));
+    }
+
+    #[test]
+    fn missing_colours() {
+        assert_eq!(
+            colour_lines(&KITTY.replace("color9 ", "colour9 "), PathBuf::new()),
+            Err("Missing or invalid color9".to_string())
+        );
+        assert_eq!(
+            alacritty(&ALACRITTY.replace("0xc0803f", "nope"), PathBuf::new()),
+            Err("Missing or invalid colors.bright.blue".to_string())
+        );
+    }
+}
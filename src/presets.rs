@@ -0,0 +1,417 @@
+use crate::colours::{
+    ColourEntry, Effect, Effects, ExtensionGroup, LsColours, TerminalColour, TerminalPalette,
+};
+
+/// A read-only starting point for new schemes
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub build: fn() -> LsColours,
+}
+
+pub const PRESETS: [Preset; 7] = [
+    Preset {
+        name: "lscoltui",
+        description: "The lscoltui defaults, using the 16 terminal colours",
+        build: LsColours::default,
+    },
+    Preset {
+        name: "GNU coreutils",
+        description: "The default database of GNU dircolors",
+        build: gnu,
+    },
+    Preset {
+        name: "Solarized",
+        description: "Ethan Schoonover's Solarized palette, in truecolor",
+        build: || themed(&SOLARIZED),
+    },
+    Preset {
+        name: "Nord",
+        description: "The arctic Nord palette, in truecolor",
+        build: || themed(&NORD),
+    },
+    Preset {
+        name: "Gruvbox",
+        description: "The retro Gruvbox dark palette, in truecolor",
+        build: || themed(&GRUVBOX),
+    },
+    Preset {
+        name: "Dracula",
+        description: "The Dracula palette, in truecolor",
+        build: || themed(&DRACULA),
+    },
+    Preset {
+        name: "Monochrome",
+        description: "No colours, only text effects, so it reads the same on any terminal",
+        build: monochrome,
+    },
+];
+
+const ARCHIVES: [&str; 46] = [
+    ".tar", ".tgz", ".arc", ".arj", ".taz", ".lha", ".lz4", ".lzh", ".lzma", ".tlz", ".txz",
+    ".tzo", ".t7z", ".zip", ".z", ".dz", ".gz", ".lrz", ".lz", ".lzo", ".xz", ".zst", ".tzst",
+    ".bz2", ".bz", ".tbz", ".tbz2", ".tz", ".deb", ".rpm", ".jar", ".war", ".ear", ".sar", ".rar",
+    ".alz", ".ace", ".zoo", ".cpio", ".7z", ".rz", ".cab", ".wim", ".swm", ".dwm", ".esd",
+];
+
+const MEDIA: [&str; 51] = [
+    ".avif", ".jpg", ".jpeg", ".mjpg", ".mjpeg", ".gif", ".bmp", ".pbm", ".pgm", ".ppm", ".tga",
+    ".xbm", ".xpm", ".tif", ".tiff", ".png", ".svg", ".svgz", ".mng", ".pcx", ".mov", ".mpg",
+    ".mpeg", ".m2v", ".mkv", ".webm", ".webp", ".ogm", ".mp4", ".m4v", ".mp4v", ".vob", ".qt",
+    ".nuv", ".wmv", ".asf", ".rm", ".rmvb", ".flc", ".avi", ".fli", ".flv", ".gl", ".dl", ".xcf",
+    ".xwd", ".yuv", ".cgm", ".emf", ".ogv", ".ogx",
+];
+
+const AUDIO: [&str; 16] = [
+    ".aac", ".au", ".flac", ".m4a", ".mid", ".midi", ".mka", ".mp3", ".mpc", ".ogg", ".ra", ".wav",
+    ".oga", ".opus", ".spx", ".xspf",
+];
+
+const BACKUPS: [&str; 17] = [
+    "~",
+    "#",
+    ".bak",
+    ".old",
+    ".orig",
+    ".part",
+    ".rej",
+    ".swp",
+    ".tmp",
+    ".dpkg-dist",
+    ".dpkg-old",
+    ".ucf-dist",
+    ".ucf-new",
+    ".ucf-old",
+    ".rpmnew",
+    ".rpmorig",
+    ".rpmsave",
+];
+
+/// The colours a themed preset draws from
+pub struct Palette {
+    pub foreground: TerminalColour,
+    pub background: TerminalColour,
+    pub muted: TerminalColour,
+    pub red: TerminalColour,
+    pub green: TerminalColour,
+    pub yellow: TerminalColour,
+    pub blue: TerminalColour,
+    pub magenta: TerminalColour,
+    pub cyan: TerminalColour,
+}
+
+const SOLARIZED: Palette = Palette {
+    foreground: TerminalColour::Rgb(0x83, 0x94, 0x96),
+    background: TerminalColour::Rgb(0x00, 0x2b, 0x36),
+    muted: TerminalColour::Rgb(0x58, 0x6e, 0x75),
+    red: TerminalColour::Rgb(0xdc, 0x32, 0x2f),
+    green: TerminalColour::Rgb(0x85, 0x99, 0x00),
+    yellow: TerminalColour::Rgb(0xb5, 0x89, 0x00),
+    blue: TerminalColour::Rgb(0x26, 0x8b, 0xd2),
+    magenta: TerminalColour::Rgb(0xd3, 0x36, 0x82),
+    cyan: TerminalColour::Rgb(0x2a, 0xa1, 0x98),
+};
+
+const NORD: Palette = Palette {
+    foreground: TerminalColour::Rgb(0xd8, 0xde, 0xe9),
+    background: TerminalColour::Rgb(0x2e, 0x34, 0x40),
+    muted: TerminalColour::Rgb(0x4c, 0x56, 0x6a),
+    red: TerminalColour::Rgb(0xbf, 0x61, 0x6a),
+    green: TerminalColour::Rgb(0xa3, 0xbe, 0x8c),
+    yellow: TerminalColour::Rgb(0xeb, 0xcb, 0x8b),
+    blue: TerminalColour::Rgb(0x81, 0xa1, 0xc1),
+    magenta: TerminalColour::Rgb(0xb4, 0x8e, 0xad),
+    cyan: TerminalColour::Rgb(0x88, 0xc0, 0xd0),
+};
+
+const GRUVBOX: Palette = Palette {
+    foreground: TerminalColour::Rgb(0xeb, 0xdb, 0xb2),
+    background: TerminalColour::Rgb(0x28, 0x28, 0x28),
+    muted: TerminalColour::Rgb(0x92, 0x83, 0x74),
+    red: TerminalColour::Rgb(0xfb, 0x49, 0x34),
+    green: TerminalColour::Rgb(0xb8, 0xbb, 0x26),
+    yellow: TerminalColour::Rgb(0xfa, 0xbd, 0x2f),
+    blue: TerminalColour::Rgb(0x83, 0xa5, 0x98),
+    magenta: TerminalColour::Rgb(0xd3, 0x86, 0x9b),
+    cyan: TerminalColour::Rgb(0x8e, 0xc0, 0x7c),
+};
+
+const DRACULA: Palette = Palette {
+    foreground: TerminalColour::Rgb(0xf8, 0xf8, 0xf2),
+    background: TerminalColour::Rgb(0x28, 0x2a, 0x36),
+    muted: TerminalColour::Rgb(0x62, 0x72, 0xa4),
+    red: TerminalColour::Rgb(0xff, 0x55, 0x55),
+    green: TerminalColour::Rgb(0x50, 0xfa, 0x7b),
+    yellow: TerminalColour::Rgb(0xf1, 0xfa, 0x8c),
+    blue: TerminalColour::Rgb(0xbd, 0x93, 0xf9),
+    magenta: TerminalColour::Rgb(0xff, 0x79, 0xc6),
+    cyan: TerminalColour::Rgb(0x8b, 0xe9, 0xfd),
+};
+
+fn sgr(sequence: &str) -> ColourEntry {
+    ColourEntry::from_sgr(sequence).unwrap()
+}
+
+fn group(name: &str, entry: ColourEntry, patterns: &[&str]) -> ExtensionGroup {
+    ExtensionGroup {
+        name: name.to_string(),
+        entry,
+        patterns: patterns.iter().map(|x| x.to_string()).collect(),
+        inherit_entry: false,
+    }
+}
+
+fn gnu() -> LsColours {
+    LsColours {
+        norm: sgr("00"),
+        rs: sgr("0"),
+        file: sgr("00"),
+        dir: sgr("01;34"),
+        link: sgr("01;36"),
+        pipe: sgr("40;33"),
+        door: sgr("01;35"),
+        block: sgr("40;33;01"),
+        char: sgr("40;33;01"),
+        orphan: sgr("40;31;01"),
+        sock: sgr("01;35"),
+        setuid: sgr("37;41"),
+        setgid: sgr("30;43"),
+        sticky_other_writable: sgr("30;42"),
+        other_writable: sgr("34;42"),
+        sticky: sgr("37;44"),
+        exec: sgr("01;32"),
+        missing: sgr("00"),
+        groups: vec![
+            group("Archives", sgr("01;31"), &ARCHIVES),
+            group("Images and video", sgr("01;35"), &MEDIA),
+            group("Audio", sgr("00;36"), &AUDIO),
+            group("Backups", sgr("00;90"), &BACKUPS),
+        ],
+        ..LsColours::default()
+    }
+}
+
+/// Builds a scheme from a terminal's palette, giving each role the ANSI colour of the same hue so
+/// the scheme follows the terminal if its palette changes. The palette is kept so the scheme
+/// previews with the same colours
+pub fn from_palette(palette: &TerminalPalette) -> LsColours {
+    let ansi = TerminalColour::ANSI;
+    let roles = Palette {
+        foreground: ansi[7],
+        background: ansi[0],
+        muted: ansi[8],
+        red: ansi[1],
+        green: ansi[2],
+        yellow: ansi[3],
+        blue: ansi[4],
+        magenta: ansi[5],
+        cyan: ansi[6],
+    };
+
+    LsColours {
+        background: palette.background.unwrap_or(palette.ansi[0]),
+        palette: Some(palette.clone()),
+        ..themed(&roles)
+    }
+}
+
+/// Brings a scheme made by `from_palette` up to date with a new palette. The kinds of file and the
+/// colours of the preset groups are made again, while slots, extensions, the patterns of groups
+/// and everything else added by hand are kept. Role slots that were removed are added back
+pub fn update_from_palette(colours: &mut LsColours, palette: &TerminalPalette) {
+    let mut generated = from_palette(palette);
+
+    // Entries refer to slots by position, which may have changed since the scheme was made
+    let mut positions = vec![];
+    for (name, colour) in &generated.slots {
+        match colours.slots.iter().position(|x| &x.0 == name) {
+            Some(i) => positions.push(i as u8),
+            None => {
+                positions.push(colours.slots.len() as u8);
+                colours.slots.push((name.clone(), *colour));
+            }
+        }
+    }
+    for entry in generated.entries_mut() {
+        for colour in [&mut entry.fg, &mut entry.bg].into_iter().flatten() {
+            if let TerminalColour::Slot(n) = colour {
+                *colour = TerminalColour::Slot(positions[*n as usize]);
+            }
+        }
+    }
+
+    let mut keys = vec![];
+    for ((key, entry), (_, new)) in colours
+        .builtins_mut()
+        .into_iter()
+        .zip(generated.builtins_mut())
+    {
+        *entry = *new;
+        keys.push(key);
+    }
+    for key in keys {
+        colours.set_override(key);
+    }
+    for group in generated.groups {
+        match colours.groups.iter_mut().find(|x| x.name == group.name) {
+            Some(x) => x.entry = group.entry,
+            None => colours.groups.push(group),
+        }
+    }
+    colours.set_override("background");
+    colours.background = generated.background;
+    colours.palette = generated.palette;
+}
+
+/// Gives each file type the palette colour of a fitting hue, in the style of the dircolors
+/// defaults
+pub fn themed(palette: &Palette) -> LsColours {
+    let plain = |fg| ColourEntry::new(Effects::NONE, Some(fg), None);
+    let bold = |fg| ColourEntry::new(Effect::Bold.into(), Some(fg), None);
+    let on = |fg, bg| ColourEntry::new(Effects::NONE, Some(fg), Some(bg));
+
+    // Entries refer to the roles through slots, so editing a role later recolours all of them.
+    // Roles are only kept readable against the background, so entries with a background of their
+    // own use the background as their text
+    let roles = [
+        ("Foreground", palette.foreground),
+        ("Background", palette.background),
+        ("Muted", palette.muted),
+        ("Red", palette.red),
+        ("Green", palette.green),
+        ("Yellow", palette.yellow),
+        ("Blue", palette.blue),
+        ("Magenta", palette.magenta),
+        ("Cyan", palette.cyan),
+    ];
+    let [foreground, background, muted, red, green, yellow, blue, magenta, cyan] =
+        std::array::from_fn(|i| TerminalColour::Slot(i as u8));
+
+    LsColours {
+        norm: plain(foreground),
+        rs: ColourEntry::default(),
+        file: plain(foreground),
+        dir: bold(blue),
+        link: bold(cyan),
+        pipe: plain(yellow),
+        door: bold(magenta),
+        block: bold(yellow),
+        char: bold(yellow),
+        orphan: bold(red),
+        sock: bold(magenta),
+        setuid: on(background, red),
+        setgid: on(background, yellow),
+        sticky_other_writable: on(background, green),
+        other_writable: on(background, green),
+        sticky: on(background, blue),
+        exec: bold(green),
+        missing: plain(red),
+        groups: vec![
+            group("Archives", bold(red), &ARCHIVES),
+            group("Images and video", bold(magenta), &MEDIA),
+            group("Audio", plain(cyan), &AUDIO),
+            group("Backups", plain(muted), &BACKUPS),
+        ],
+        background: palette.background,
+        slots: roles
+            .map(|(name, colour)| (name.to_string(), colour))
+            .to_vec(),
+        ..LsColours::default()
+    }
+}
+
+fn monochrome() -> LsColours {
+    let with = |effects: &[Effect]| {
+        let mut entry = ColourEntry::default();
+        for effect in effects {
+            entry.effects.insert(*effect);
+        }
+        entry
+    };
+
+    LsColours {
+        norm: with(&[]),
+        rs: with(&[]),
+        file: with(&[]),
+        dir: with(&[Effect::Bold]),
+        link: with(&[Effect::Italic]),
+        pipe: with(&[Effect::Underline]),
+        door: with(&[Effect::Underline]),
+        block: with(&[Effect::Bold, Effect::Underline]),
+        char: with(&[Effect::Bold, Effect::Underline]),
+        orphan: with(&[Effect::Italic, Effect::Strikethrough]),
+        sock: with(&[Effect::Underline]),
+        setuid: with(&[Effect::Reverse]),
+        setgid: with(&[Effect::Reverse]),
+        sticky_other_writable: with(&[Effect::Bold, Effect::Reverse]),
+        other_writable: with(&[Effect::Reverse]),
+        sticky: with(&[Effect::Bold, Effect::Reverse]),
+        exec: with(&[Effect::Bold, Effect::Italic]),
+        missing: with(&[Effect::Strikethrough]),
+        groups: vec![group("Backups", with(&[Effect::Dim]), &BACKUPS)],
+        ..LsColours::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(blue: TerminalColour) -> TerminalPalette {
+        let mut ansi = [TerminalColour::Rgb(10, 10, 10); 16];
+        ansi[4] = blue;
+        TerminalPalette {
+            source: "colors.json".into(),
+            ansi,
+            foreground: None,
+            background: Some(TerminalColour::Rgb(1, 2, 3)),
+        }
+    }
+
+    #[test]
+    fn from_palette_uses_ansi_colours() {
+        let colours = from_palette(&palette(TerminalColour::Rgb(0, 0, 255)));
+        assert_eq!(
+            colours.slot_colour(colours.dir.fg.unwrap()),
+            TerminalColour::Blue
+        );
+        assert!(colours.parse().contains("di=01;34"));
+        assert_eq!(colours.background, TerminalColour::Rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn update_keeps_changes_made_by_hand() {
+        let mut colours = from_palette(&palette(TerminalColour::Rgb(0, 0, 255)));
+        colours
+            .extensions
+            .push((".rs".to_string(), ColourEntry::default()));
+        colours.groups[0].patterns.push(".zst".to_string());
+        colours
+            .groups
+            .push(group("Mine", ColourEntry::default(), &[".x"]));
+        colours.eza = Some(vec![]);
+        colours.remove_slot(0);
+        colours.slots[0].1 = TerminalColour::Indexed(100);
+        colours
+            .slots
+            .push(("Extra".to_string(), TerminalColour::White));
+        colours.dir = ColourEntry::default();
+
+        let mut updated = colours.clone();
+        update_from_palette(&mut updated, &palette(TerminalColour::Rgb(0, 0, 200)));
+
+        assert_eq!(updated.extensions, colours.extensions);
+        assert!(updated.groups[0].patterns.contains(&".zst".to_string()));
+        assert!(updated.groups.iter().any(|x| x.name == "Mine"));
+        assert_eq!(updated.eza, Some(vec![]));
+        assert_eq!(updated.slots[..9], colours.slots[..]);
+        assert_eq!(updated.slots[9].0, "Foreground");
+        assert_eq!(
+            updated.palette.as_ref().unwrap().ansi[4],
+            TerminalColour::Rgb(0, 0, 200)
+        );
+        // The kinds of file are made again, referring to the slots where they are now
+        assert!(updated.parse().contains("di=01;34"));
+        assert!(updated.parse().contains("no=00;37"));
+    }
+}
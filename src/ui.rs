@@ -1,12 +1,23 @@
 use crossterm::event::{self, Event, KeyEventKind};
 use ratatui::{
+    layout::Rect,
     style::{Color, Modifier, Stylize},
-    text::Line,
+    text::{Line, Span},
     widgets::*,
     DefaultTerminal, Frame,
 };
 
-use crate::{colours::ColourEntry, file::SaveFile, key_events};
+use crate::{
+    check,
+    colours::{
+        valid_escape, ColourEntry, Effect, LsColours, PatternKind, TerminalColour, Vision,
+        MIN_CONTRAST,
+    },
+    eza,
+    file::{self, SaveFile, Shell},
+    key_events, lint,
+    presets::PRESETS,
+};
 
 const FG_COL: Color = Color::White;
 
@@ -17,6 +28,14 @@ pub struct App {
     pub dialog_state: DialogState,
     pub content_loc: i32,
     pub active_lce: Option<ListColourEntry>,
+    pub hex_input: String,
+    pub expanded_groups: Vec<String>,
+    /// Colour vision the scheme is previewed with
+    pub vision: Vision,
+    /// Shell the Export dialog writes the command for
+    pub shell: Shell,
+    /// Message about the last key pressed, shown until the next one
+    pub status: Option<String>,
 }
 
 impl Default for App {
@@ -28,6 +47,11 @@ impl Default for App {
             dialog_state: DialogState::Closed,
             content_loc: 0,
             active_lce: None,
+            hex_input: String::new(),
+            expanded_groups: vec![],
+            vision: Vision::Normal,
+            shell: Shell::Bash,
+            status: None,
         }
     }
 }
@@ -35,9 +59,17 @@ impl Default for App {
 pub enum DialogState {
     Closed,
     PickScheme(u8),
-    NewScheme(String),
-    NewExtension(String),
+    /// Name of the new scheme and the index of the preset it starts from
+    NewScheme(String, usize),
+    ImportEnv(String),
+    NewExtension(String, PatternKind),
+    NewGroup(String),
     EditingColour(u8),
+    EditingEscape(String),
+    EditingBackground,
+    /// Index of the selected slot in the palette editor
+    EditingSlots(u8),
+    NewSlot(String),
     Export,
 }
 
@@ -46,9 +78,52 @@ pub struct ListColourEntry {
     pub entry: ColourEntry,
     pub text: String,
     pub description: String,
+    pub kind: EntryKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryKind {
+    /// A kind of file that ls colours, holding its `LS_COLORS` key
+    Builtin(&'static str),
+    /// A raw escape sequence that ls writes around names, holding its `LS_COLORS` key and the
+    /// sequence if one is set
+    Escape(&'static str, Option<String>),
+    /// A rule from `LsColours::extensions`, holding its pattern
+    Extension(String),
+    /// An extension group, holding its name
+    Group(String),
+    /// A pattern belonging to an expanded group, holding the group name and the pattern
+    GroupMember(String, String),
+    /// An entry of the eza section, holding its `EZA_COLORS` key
+    Eza(&'static str),
+}
+
+impl ListColourEntry {
+    /// Name of the group this row belongs to, if any
+    pub fn group(&self) -> Option<&String> {
+        match &self.kind {
+            EntryKind::Group(name) | EntryKind::GroupMember(name, _) => Some(name),
+            _ => None,
+        }
+    }
 }
 
 impl App {
+    /// The open scheme with everything it inherits filled in, or as it is stored if its parents
+    /// can't be found
+    pub fn resolved_scheme(&self) -> Option<LsColours> {
+        let name = self.open_scheme.as_ref()?;
+        match self.savefile.resolve(name) {
+            Ok(colours) => Some(colours),
+            Err(_) => self
+                .savefile
+                .schemes
+                .iter()
+                .find(|x| &x.0 == name)
+                .map(|x| x.1.clone()),
+        }
+    }
+
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
         while !self.exit {
             terminal.draw(|frame| self.draw(frame))?;
@@ -60,274 +135,96 @@ impl App {
     fn draw(&mut self, frame: &mut Frame) {
         let area = frame.area();
 
-        let title = Line::from("lscoltui".bold());
+        let title = match self.vision {
+            Vision::Normal => Line::from("lscoltui".bold()),
+            vision => Line::from(vec![
+                "lscoltui".bold(),
+                format!(" (Simulating {})", vision).into(),
+            ]),
+        };
         #[rustfmt::skip]
         let instructions = Line::from(vec![
-            " Open Scheme ".into(), "<F1> ".blue().bold(),
-            " New Scheme ".into(), "<F2> ".blue().bold(),
+            " Open ".into(), "<F1> ".blue().bold(),
+            " New ".into(), "<F2> ".blue().bold(),
             " Add Extension ".into(), "<F3> ".blue().bold(),
-            " Export Scheme ".into(), "<F4> ".blue().bold(),
+            " Export ".into(), "<F4> ".blue().bold(),
             " Quit ".into(), "<F5> ".blue().bold(),
-            " Edit Colour ".into(), "<Enter>".blue().bold(),
-            " Delete Extension ".into(), "<Delete>".blue().bold(),
+            " New Group ".into(), "<F6> ".blue().bold(),
+            " Background ".into(), "<F7> ".blue().bold(),
+            " Palette ".into(), "<F8> ".blue().bold(),
+            " Expand ".into(), "<Space> ".blue().bold(),
+            " Vision ".into(), "<V> ".blue().bold(),
+            " eza ".into(), "<E> ".blue().bold(),
+            " Edit ".into(), "<Enter>".blue().bold(),
+            " Delete ".into(), "<Delete>".blue().bold(),
         ]);
 
         let mut colour_lines = vec![];
+        let mut contrast_line = Line::raw("");
+        let mut inherited_line = Line::raw("");
+        let mut clashes = vec![];
 
         if self.open_scheme.is_some() {
-            let active_scheme = &self
-                .savefile
-                .schemes
-                .iter()
-                .find(|x| &x.0 == self.open_scheme.as_ref().unwrap())
-                .unwrap()
-                .1;
+            let active_scheme = &self.resolved_scheme().unwrap();
+
+            let entries = scheme_entries(active_scheme, &self.expanded_groups);
 
-            if self.content_loc >= active_scheme.extensions.len() as i32 + 18 {
+            if self.content_loc >= entries.len() as i32 {
                 self.content_loc = 0;
             }
 
             if self.content_loc < 0 {
-                self.content_loc = 18 + active_scheme.extensions.len() as i32 - 1;
+                self.content_loc = entries.len() as i32 - 1;
+            }
+
+            for (i, entry) in entries.into_iter().enumerate() {
+                let contrast = entry_contrast(active_scheme, &entry);
+                let shown = self
+                    .vision
+                    .simulate_entry(active_scheme.preview(entry.entry));
+                colour_lines.push(colour_entry_to_line(
+                    entry,
+                    shown,
+                    contrast,
+                    self.content_loc,
+                    i as i32,
+                    &mut self.active_lce,
+                ));
             }
 
-            let normal = ListColourEntry {
-                entry: active_scheme.norm,
-                text: "Normal".to_string(),
-                description:
-                    "Global default, though everything should be set so it should be rarely used"
-                        .to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                normal,
-                self.content_loc,
-                0,
-                &mut self.active_lce,
-            ));
-
-            let file = ListColourEntry {
-                entry: active_scheme.file,
-                text: "File".to_string(),
-                description: "Normal file".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                file,
-                self.content_loc,
-                1,
-                &mut self.active_lce,
-            ));
-
-            let dir = ListColourEntry {
-                entry: active_scheme.dir,
-                text: "Directory".to_string(),
-                description: "Normal directory".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                dir,
-                self.content_loc,
-                2,
-                &mut self.active_lce,
-            ));
-
-            let link = ListColourEntry {
-                entry: active_scheme.link,
-                text: "Symlink".to_string(),
-                description: "A symbolic link to another file on the filesystem".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                link,
-                self.content_loc,
-                3,
-                &mut self.active_lce,
-            ));
-
-            let pipe = ListColourEntry {
-                entry: active_scheme.pipe,
-                text: "Pipe".to_string(),
-                description: "A named pipe".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                pipe,
-                self.content_loc,
-                4,
-                &mut self.active_lce,
-            ));
-
-            let door = ListColourEntry {
-                entry: active_scheme.door,
-                text: "Door".to_string(),
-                description: "A door file".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                door,
-                self.content_loc,
-                5,
-                &mut self.active_lce,
-            ));
-
-            let block = ListColourEntry {
-                entry: active_scheme.block,
-                text: "Block".to_string(),
-                description: "A block device file".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                block,
-                self.content_loc,
-                6,
-                &mut self.active_lce,
-            ));
-
-            let char = ListColourEntry {
-                entry: active_scheme.char,
-                text: "Character".to_string(),
-                description: "A character device file".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                char,
-                self.content_loc,
-                7,
-                &mut self.active_lce,
-            ));
-
-            let orphan = ListColourEntry {
-                entry: active_scheme.orphan,
-                text: "Orphaned Symlink".to_string(),
-                description: "A symbolic link pointing to a non-existent file".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                orphan,
-                self.content_loc,
-                8,
-                &mut self.active_lce,
-            ));
-
-            let socket = ListColourEntry {
-                entry: active_scheme.sock,
-                text: "Socket".to_string(),
-                description: "A socket file".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                socket,
-                self.content_loc,
-                9,
-                &mut self.active_lce,
-            ));
-
-            let setuid = ListColourEntry {
-                entry: active_scheme.setuid,
-                text: "SetUID".to_string(),
-                description: "A file with the SetUID bit enabled".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                setuid,
-                self.content_loc,
-                10,
-                &mut self.active_lce,
-            ));
-
-            let setgid = ListColourEntry {
-                entry: active_scheme.setgid,
-                text: "SetGID".to_string(),
-                description: "A file with the SetGID bit enabled".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                setgid,
-                self.content_loc,
-                11,
-                &mut self.active_lce,
-            ));
-
-            let sticky_other_writable = ListColourEntry {
-                entry: active_scheme.sticky_other_writable,
-                text: "Sticky Other Writable".to_string(),
-                description: "A directory that is sticky (only the owner can delete files), but that others can write to".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                sticky_other_writable,
-                self.content_loc,
-                12,
-                &mut self.active_lce,
-            ));
-
-            let other_writable = ListColourEntry {
-                entry: active_scheme.other_writable,
-                text: "Other Writable".to_string(),
-                description: "A directory that isn't sticky, and others can write to it"
-                    .to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                other_writable,
-                self.content_loc,
-                13,
-                &mut self.active_lce,
-            ));
-
-            let sticky = ListColourEntry {
-                entry: active_scheme.sticky,
-                text: "Sticky".to_string(),
-                description: "A directory that is sticky (only the owner can delete files), but that others are unable to write to".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                sticky,
-                self.content_loc,
-                14,
-                &mut self.active_lce,
-            ));
-
-            let exec = ListColourEntry {
-                entry: active_scheme.exec,
-                text: "Executable".to_string(),
-                description: "An executable file".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                exec,
-                self.content_loc,
-                15,
-                &mut self.active_lce,
-            ));
-
-            let missing = ListColourEntry {
-                entry: active_scheme.missing,
-                text: "Missing".to_string(),
-                description: "A non-existent file pointed to by a symbolic link".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                missing,
-                self.content_loc,
-                16,
-                &mut self.active_lce,
-            ));
-
-            let endcode = ListColourEntry {
-                entry: active_scheme.rs,
-                text: "End Code".to_string(),
-                description: "Text that isn't filenames (e.g. \'/\')".to_string(),
-            };
-            colour_lines.push(colour_entry_to_line(
-                endcode,
-                self.content_loc,
-                17,
-                &mut self.active_lce,
-            ));
-
-            let _ = active_scheme
-                .extensions
+            clashes = lint::lint(active_scheme);
+
+            if let Some(contrast) = self
+                .active_lce
+                .as_ref()
+                .and_then(|x| entry_contrast(active_scheme, x))
+            {
+                contrast_line = contrast_to_line(contrast, active_scheme);
+            }
+
+            let stored = &self
+                .savefile
+                .schemes
                 .iter()
-                .enumerate()
-                .map(|(i, x)| {
-                    let y = ListColourEntry {
-                        entry: x.1,
-                        text: x.clone().0,
-                        description: format!("Colour of .{} files", x.clone().0),
-                    };
-                    colour_lines.push(colour_entry_to_line(
-                        y,
-                        self.content_loc,
-                        i as i32 + 18,
-                        &mut self.active_lce,
-                    ));
-                })
-                .collect::<Vec<_>>();
+                .find(|x| &x.0 == self.open_scheme.as_ref().unwrap())
+                .unwrap()
+                .1;
+            if let (Some(parent), Some(lce)) = (&stored.parent, &self.active_lce) {
+                if inherited(stored, lce) {
+                    inherited_line = Line::raw(format!(
+                        "{} \'{}\'. Editing it overrides it in this scheme",
+                        match stored.variant {
+                            true => "Derived from",
+                            false => "Inherited from",
+                        },
+                        parent
+                    ))
+                    .dim();
+                }
+            }
+            if let Some(status) = &self.status {
+                inherited_line = Line::raw(status.clone()).yellow();
+            }
         }
 
         let content_block = Block::new()
@@ -342,14 +239,22 @@ impl App {
             colour_lines = vec![]
         }
 
+        // Scroll just far enough to keep the selected entry on screen
+        let scroll = (self.content_loc + 1 - content_inner.height as i32).max(0) as u16;
+
         let content = Paragraph::new(colour_lines)
             .left_aligned()
-            .wrap(Wrap { trim: false });
-
-        let content_desc = Paragraph::new(match self.active_lce.clone() {
-            Some(x) => x.description,
-            None => String::new(),
-        })
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0));
+
+        let content_desc = Paragraph::new(vec![
+            Line::raw(match self.active_lce.clone() {
+                Some(x) => x.description,
+                None => String::new(),
+            }),
+            contrast_line.clone(),
+            inherited_line,
+        ])
         .right_aligned()
         .wrap(Wrap { trim: true });
 
@@ -361,17 +266,23 @@ impl App {
                 let mut lines = vec![];
                 let mut i = 0;
                 while i < schemes.len() {
-                    lines.push(Line::raw(schemes[i].0.clone()).white().fg(
-                        match i == *x as usize {
+                    let link = match &schemes[i].1.parent {
+                        Some(parent) if schemes[i].1.variant => format!(" (variant of {})", parent),
+                        Some(parent) => format!(" (inherits from {})", parent),
+                        None => String::new(),
+                    };
+                    lines.push(Line::from(vec![
+                        Span::raw(schemes[i].0.clone()).fg(match i == *x as usize {
                             true => Color::LightBlue,
                             false => FG_COL,
-                        },
-                    ));
+                        }),
+                        Span::raw(link).dim(),
+                    ]));
 
                     i += 1;
                 }
 
-                if schemes.len() == 0 {
+                if schemes.is_empty() {
                     lines.push(Line::raw("You have no schemes. Create one!"))
                 }
 
@@ -381,23 +292,27 @@ impl App {
                     " Move Down ".into(), "<Down> ".blue().bold(),
                     " Select ".into(), "<Enter> ".blue().bold(),
                     " Delete ".into(), "<Delete> ".blue().bold(),
+                    " Import $LS_COLORS ".into(), "<I> ".blue().bold(),
+                    " Light/Dark Variant ".into(), "<D> ".blue().bold(),
                     " Exit ".into(), "<Escape> ".blue().bold(),
                 ]);
                 dialog_instructions = instructions;
 
                 Paragraph::new(lines).left_aligned()
             }
-            DialogState::NewScheme(s) => {
+            DialogState::ImportEnv(s) => {
                 #[rustfmt::skip]
                 let instructions = Line::from(vec![
-                    " Create ".into(), "<Enter> ".blue().bold(),
+                    " Import ".into(), "<Enter> ".blue().bold(),
                     " Exit ".into(), "<Escape> ".blue().bold(),
                 ]);
                 dialog_instructions = instructions;
                 Paragraph::new(vec![
-                    Line::raw("Enter name of new scheme: ").bold(),
+                    Line::raw("Enter name for the scheme imported from $LS_COLORS: ").bold(),
                     Line::raw(format!("{}|", s)),
-                    if self.savefile.schemes.iter().find(|x| &x.0 == s).is_some() {
+                    if let Err(e) = file::env_colours() {
+                        Line::raw(e).red()
+                    } else if self.savefile.schemes.iter().any(|x| &x.0 == s) {
                         Line::raw("Scheme of same name exists").red()
                     } else if s.is_empty() {
                         Line::raw("Name cannot be empty").red()
@@ -407,53 +322,135 @@ impl App {
                 ])
                 .centered()
             }
-            DialogState::NewExtension(s) => {
+            DialogState::NewScheme(s, preset) => {
+                #[rustfmt::skip]
+                let instructions = Line::from(vec![
+                    " Create ".into(), "<Enter> ".blue().bold(),
+                    " Pick Preset ".into(), "<Up/Down> ".blue().bold(),
+                    " Exit ".into(), "<Escape> ".blue().bold(),
+                ]);
+                dialog_instructions = instructions;
+
+                let mut lines = vec![
+                    Line::raw("Enter name of new scheme: ").bold(),
+                    Line::raw(format!("{}|", s)),
+                    if self.savefile.schemes.iter().find(|x| &x.0 == s).is_some() {
+                        Line::raw("Scheme of same name exists").red()
+                    } else if s.is_empty() {
+                        Line::raw("Name cannot be empty").red()
+                    } else {
+                        Line::raw("")
+                    },
+                    Line::raw(""),
+                    Line::raw("Start from preset: ").bold(),
+                ];
+
+                for (i, x) in PRESETS.iter().enumerate() {
+                    lines.push(Line::raw(x.name).fg(match i == *preset {
+                        true => Color::LightBlue,
+                        false => FG_COL,
+                    }));
+                }
+
+                lines.push(Line::raw(""));
+                lines.push(Line::raw(PRESETS[*preset].description));
+
+                Paragraph::new(lines).centered()
+            }
+            DialogState::NewExtension(s, kind) => {
                 if self.open_scheme.as_ref().is_some() {
-                    let active_scheme = &self
-                        .savefile
-                        .schemes
-                        .iter()
-                        .find(|x| &x.0 == self.open_scheme.as_ref().unwrap())
-                        .unwrap()
-                        .1;
+                    let active_scheme = &self.resolved_scheme().unwrap();
 
                     #[rustfmt::skip]
                     let instructions = Line::from(vec![
                         " Create ".into(), "<Enter> ".blue().bold(),
+                        " Extension/Pattern ".into(), "<Tab> ".blue().bold(),
                         " Exit ".into(), "<Escape> ".blue().bold(),
                     ]);
 
                     dialog_instructions = instructions;
-                    Paragraph::new(vec![
-                        Line::raw("Enter name of new extension: ").bold(),
+                    let pattern = kind.normalise(s);
+                    let group = self.active_lce.as_ref().and_then(|x| x.group());
+                    let mut lines = vec![
+                        Line::raw(match kind {
+                            PatternKind::Extension => "Enter new extension (e.g. tar.gz): ",
+                            PatternKind::Glob => "Enter new pattern (e.g. Makefile, *README*, ~): ",
+                        })
+                        .bold(),
+                        match group {
+                            Some(name) => Line::raw(format!("Adding to group {}", name)),
+                            None => Line::raw(""),
+                        },
                         Line::raw(format!("{}|", s)),
-                        if active_scheme
-                            .extensions
-                            .iter()
-                            .find(|x| &x.0 == s)
-                            .is_some()
-                        {
-                            Line::raw("Extension of same name exists").red()
-                        } else if s.is_empty() {
+                        if active_scheme.contains_pattern(group.map(|x| x.as_str()), &pattern) {
+                            Line::raw(format!("{} of same name exists", kind)).red()
+                        } else if s.trim().trim_start_matches(['*', '.']).is_empty() {
                             Line::raw("Name cannot be empty").red()
+                        } else if let Err(e) = check::validate_pattern(&pattern) {
+                            Line::raw(e).red()
                         } else {
-                            Line::raw("")
+                            Line::raw(format!("Matches {}", pattern_description(&pattern)))
                         },
-                    ])
-                    .centered()
+                    ];
+
+                    if check::validate_pattern(&pattern).is_ok() {
+                        for problem in
+                            check::new_problems(active_scheme, group.map(|x| x.as_str()), &pattern)
+                        {
+                            lines.push(Line::raw(problem.message).yellow());
+                        }
+                    }
+
+                    Paragraph::new(lines).centered().wrap(Wrap { trim: true })
                 } else {
                     self.dialog_state = DialogState::Closed;
                     Paragraph::new(vec![])
                 }
             }
+            DialogState::NewGroup(s) => {
+                #[rustfmt::skip]
+                let instructions = Line::from(vec![
+                    " Create ".into(), "<Enter> ".blue().bold(),
+                    " Exit ".into(), "<Escape> ".blue().bold(),
+                ]);
+                dialog_instructions = instructions;
+
+                let exists = match &self.open_scheme {
+                    Some(name) => self
+                        .savefile
+                        .schemes
+                        .iter()
+                        .find(|x| &x.0 == name)
+                        .is_some_and(|x| x.1.groups.iter().any(|x| &x.name == s)),
+                    None => false,
+                };
+                Paragraph::new(vec![
+                    Line::raw("Enter name of new group (e.g. Archives): ").bold(),
+                    Line::raw(format!("{}|", s)),
+                    if exists {
+                        Line::raw("Group of same name exists").red()
+                    } else if s.is_empty() {
+                        Line::raw("Name cannot be empty").red()
+                    } else {
+                        Line::raw("Select the group and press F3 to add patterns to it")
+                    },
+                ])
+                .centered()
+            }
             DialogState::EditingColour(i) => match &self.active_lce {
                 Some(lce) => {
+                    let active_scheme = &self.resolved_scheme().unwrap();
                     let entry = lce.entry;
+                    let preview = self.vision.simulate_entry(active_scheme.preview(entry));
 
                     #[rustfmt::skip]
                     let instructions = Line::from(vec![
-                        " Switch Backwards ".into(), "<Left> ".blue().bold(),
-                        " Switch Forwards ".into(), "<Right> ".blue().bold(),
+                        " Switch Backwards / Toggle ".into(), "<Left> ".blue().bold(),
+                        " Switch Forwards / Toggle ".into(), "<Right> ".blue().bold(),
+                        " Named/256/RGB/Slot ".into(), "<Tab> ".blue().bold(),
+                        " Hex ".into(), "<#> ".blue().bold(),
+                        " Apply Hex ".into(), "<Enter> ".blue().bold(),
+                        " Jump 16 ".into(), "<PgUp/PgDn> ".blue().bold(),
                         " Exit (Changes automatically saved) ".into(), "<Escape> ".blue().bold(),
                     ]);
 
@@ -466,34 +463,25 @@ impl App {
 
                     lines.push(
                         Line::raw("Preview")
-                            .fg(match entry.fg {
+                            .fg(match preview.fg {
                                 Some(c) => c.into(),
                                 None => Color::Reset,
                             })
-                            .bg(match entry.bg {
+                            .bg(match preview.bg {
                                 Some(c) => c.into(),
                                 None => Color::Reset,
                             })
-                            .add_modifier(match entry.effects {
-                                crate::colours::Effects::Default => Modifier::empty(),
-                                crate::colours::Effects::Bold => Modifier::BOLD,
-                                crate::colours::Effects::Underline => Modifier::UNDERLINED,
-                                crate::colours::Effects::Italic => Modifier::ITALIC,
-                                crate::colours::Effects::Strikethrough => Modifier::CROSSED_OUT,
-                            }),
+                            .add_modifier(preview.effects.modifier()),
                     );
 
-                    lines.push(Line::raw(""));
+                    lines.push(contrast_line);
 
                     lines.push(
                         Line::raw(format!(
                             "Foreground: {}",
-                            match entry.fg {
-                                Some(c) => c.to_string(),
-                                None => "None".to_string(),
-                            }
+                            colour_label(active_scheme, entry.fg)
                         ))
-                        .fg(match entry.fg {
+                        .fg(match preview.fg {
                             Some(c) => c.into(),
                             None => Color::Reset,
                         })
@@ -506,12 +494,9 @@ impl App {
                     lines.push(
                         Line::raw(format!(
                             "Background: {}",
-                            match entry.bg {
-                                Some(c) => c.to_string(),
-                                None => "None".to_string(),
-                            }
+                            colour_label(active_scheme, entry.bg)
                         ))
-                        .fg(match entry.bg {
+                        .fg(match preview.bg {
                             Some(c) => c.into(),
                             None => Color::Reset,
                         })
@@ -521,30 +506,224 @@ impl App {
                         }),
                     );
 
+                    if *i < 2 {
+                        let selected = match *i {
+                            0 => entry.fg,
+                            _ => entry.bg,
+                        };
+                        lines.push(Line::raw(if !self.hex_input.is_empty() {
+                            format!("Hex: {}|", self.hex_input)
+                        } else {
+                            format!(
+                                "Hex: {}",
+                                match selected {
+                                    Some(c) => active_scheme.rgb(c).to_hex(),
+                                    None => "None".to_string(),
+                                }
+                            )
+                        }));
+                    } else {
+                        lines.push(Line::raw(""));
+                    }
+
+                    lines.push(Line::raw(""));
+                    lines.push(Line::raw("Effects:"));
+
+                    for (n, effect) in Effect::ORDER.iter().enumerate() {
+                        lines.push(
+                            Line::raw(format!(
+                                "[{}] {}",
+                                match entry.effects.contains(*effect) {
+                                    true => 'x',
+                                    false => ' ',
+                                },
+                                effect
+                            ))
+                            .add_modifier(
+                                match *i as usize == n + 2 {
+                                    true => Modifier::UNDERLINED,
+                                    false => Modifier::empty(),
+                                },
+                            ),
+                        );
+                    }
+
+                    Paragraph::new(lines)
+                }
+                None => Paragraph::new(vec![]),
+            },
+            DialogState::EditingEscape(s) => {
+                #[rustfmt::skip]
+                let instructions = Line::from(vec![
+                    " Save ".into(), "<Enter> ".blue().bold(),
+                    " Exit ".into(), "<Escape> ".blue().bold(),
+                ]);
+                dialog_instructions = instructions;
+                Paragraph::new(vec![
+                    Line::raw(format!(
+                        "Enter escape sequence for {}: ",
+                        match &self.active_lce {
+                            Some(lce) => lce.text.clone(),
+                            None => String::new(),
+                        }
+                    ))
+                    .bold(),
+                    Line::raw(format!("{}|", s)),
+                    match valid_escape(s) {
+                        true => Line::raw("Leave empty to use the ls default"),
+                        false => Line::raw("\':\' and \'=\' have to be escaped with a backslash")
+                            .yellow(),
+                    },
+                ])
+                .centered()
+            }
+            DialogState::EditingBackground => {
+                let active_scheme = &self.resolved_scheme().unwrap();
+
+                #[rustfmt::skip]
+                let instructions = Line::from(vec![
+                    " Switch Backwards ".into(), "<Left> ".blue().bold(),
+                    " Switch Forwards ".into(), "<Right> ".blue().bold(),
+                    " Hex ".into(), "<#> ".blue().bold(),
+                    " Apply Hex ".into(), "<Enter> ".blue().bold(),
+                    " Exit (Changes automatically saved) ".into(), "<Escape> ".blue().bold(),
+                ]);
+                dialog_instructions = instructions;
+
+                let background = active_scheme.background;
+                let palette = match &active_scheme.palette {
+                    Some(palette) => Line::from(
+                        [Span::raw(format!("Palette from {} ", palette.source.display()))]
+                            .into_iter()
+                            .chain(palette.ansi.iter().map(|x| Span::raw("  ").bg(Color::from(*x))))
+                            .collect::<Vec<_>>(),
+                    ),
+                    None => Line::raw(
+                        "No palette. Set one with \'lscoltui palette <scheme> <file>\' to preview with your terminal\'s colours",
+                    ),
+                };
+                let low = scheme_entries(active_scheme, &[])
+                    .iter()
+                    .filter(|x| entry_contrast(active_scheme, x).is_some_and(|x| x < MIN_CONTRAST))
+                    .count();
+
+                Paragraph::new(vec![
+                    Line::raw("Terminal Background").bold(),
+                    Line::raw(""),
+                    Line::raw("The background colour of the terminal this scheme is meant for. It is only used to check the contrast of entries without a background of their own"),
+                    Line::raw(""),
+                    Line::raw(format!("Background: {}", background)),
+                    Line::raw(if !self.hex_input.is_empty() {
+                        format!("Hex: {}|", self.hex_input)
+                    } else {
+                        format!("Hex: {}", active_scheme.rgb(background).to_hex())
+                    }),
+                    Line::raw("        ").bg(Color::from(
+                        self.vision.simulate(active_scheme.rgb(background)),
+                    )),
+                    Line::raw(""),
+                    palette,
+                    Line::raw(""),
+                    match low {
+                        0 => Line::raw("Every entry is readable on this background"),
+                        n => Line::raw(format!("{} entries have low contrast on this background", n))
+                            .red(),
+                    },
+                ])
+                .centered()
+                .wrap(Wrap { trim: true })
+            }
+            DialogState::EditingSlots(i) => {
+                let active_scheme = &self.resolved_scheme().unwrap();
+
+                #[rustfmt::skip]
+                let instructions = Line::from(vec![
+                    " Select ".into(), "<Up/Down> ".blue().bold(),
+                    " Switch ".into(), "<Left/Right> ".blue().bold(),
+                    " Named/256/RGB ".into(), "<Tab> ".blue().bold(),
+                    " Hex ".into(), "<#> ".blue().bold(),
+                    " Apply Hex ".into(), "<Enter> ".blue().bold(),
+                    " New ".into(), "<+> ".blue().bold(),
+                    " Delete ".into(), "<Delete> ".blue().bold(),
+                    " Exit (Changes automatically saved) ".into(), "<Escape> ".blue().bold(),
+                ]);
+                dialog_instructions = instructions;
+
+                let mut lines = vec![
+                    Line::raw("Palette").bold(),
+                    Line::raw(""),
+                    Line::raw("Named colours that entries can use in place of a colour of their own. Editing one recolours every entry using it, and deleting one gives those entries its colour"),
+                    Line::raw(""),
+                ];
+
+                if active_scheme.slots.is_empty() {
+                    lines.push(Line::raw("No slots yet. Press + to add one"));
+                }
+
+                for (n, (name, colour)) in active_scheme.slots.iter().enumerate() {
+                    let swatch = self.vision.simulate(active_scheme.rgb(*colour));
                     lines.push(
-                        Line::raw(format!(
-                            "Effect: {}",
-                            match entry.effects {
-                                crate::colours::Effects::Default => "None",
-                                crate::colours::Effects::Bold => "Bold",
-                                crate::colours::Effects::Underline => "Underline",
-                                crate::colours::Effects::Italic => "Italic",
-                                crate::colours::Effects::Strikethrough => "Strikethrough",
-                            }
-                        ))
-                        .add_modifier(match *i == 2 {
+                        Line::from(vec![
+                            Span::raw("    ").bg(Color::from(swatch)),
+                            Span::raw(format!(
+                                " {}: {}, used by {} entries",
+                                name,
+                                colour_label(active_scheme, Some(*colour)),
+                                active_scheme.slot_uses(n as u8)
+                            )),
+                        ])
+                        .add_modifier(match *i as usize == n {
                             true => Modifier::UNDERLINED,
                             false => Modifier::empty(),
                         }),
                     );
+                }
 
-                    Paragraph::new(lines)
+                if let Some((_, colour)) = active_scheme.slots.get(*i as usize) {
+                    lines.push(Line::raw(""));
+                    lines.push(Line::raw(if !self.hex_input.is_empty() {
+                        format!("Hex: {}|", self.hex_input)
+                    } else {
+                        format!("Hex: {}", active_scheme.rgb(*colour).to_hex())
+                    }));
                 }
-                None => Paragraph::new(vec![]),
-            },
+
+                Paragraph::new(lines).centered().wrap(Wrap { trim: true })
+            }
+            DialogState::NewSlot(s) => {
+                #[rustfmt::skip]
+                let instructions = Line::from(vec![
+                    " Create ".into(), "<Enter> ".blue().bold(),
+                    " Exit ".into(), "<Escape> ".blue().bold(),
+                ]);
+                dialog_instructions = instructions;
+
+                let exists = match &self.open_scheme {
+                    Some(name) => self
+                        .savefile
+                        .schemes
+                        .iter()
+                        .find(|x| &x.0 == name)
+                        .is_some_and(|x| x.1.slots.iter().any(|x| &x.0 == s)),
+                    None => false,
+                };
+                Paragraph::new(vec![
+                    Line::raw("Enter name of new slot (e.g. Accent): ").bold(),
+                    Line::raw(format!("{}|", s)),
+                    if exists {
+                        Line::raw("Slot of same name exists").red()
+                    } else if s.is_empty() {
+                        Line::raw("Name cannot be empty").red()
+                    } else {
+                        Line::raw("Pick it with Tab when editing an entry's colour")
+                    },
+                ])
+                .centered()
+            }
             DialogState::Export => {
                 #[rustfmt::skip]
                 let instructions = Line::from(vec![
+                    " Switch Shell ".into(), "<Tab> ".blue().bold(),
                     " Exit ".into(), "<Escape> ".blue().bold(),
                 ]);
 
@@ -555,24 +734,22 @@ impl App {
 
                 lines.push(Line::raw(""));
 
+                lines.push(Line::raw(format!("Shell: {}", self.shell)));
+
+                lines.push(Line::raw(""));
+
                 lines.push(Line::raw("Here is the command to set the colours of ls:").bold());
 
-                lines.push(Line::raw(format!(
-                    "export LS_COLORS=\'{}\'",
-                    self.savefile
-                        .schemes
-                        .iter()
-                        .find(|x| &x.0 == self.open_scheme.as_ref().unwrap())
-                        .unwrap()
-                        .1
-                        .parse()
+                lines.push(Line::raw(file::env_command(
+                    &self.resolved_scheme().unwrap(),
+                    self.shell,
                 )));
 
                 lines.push(Line::raw(""));
 
                 lines.push(Line::raw(format!(
-                    "To run this automatically, place \'eval $(lscoltui export {})\' in your terminal startup file, such as .bashrc for Bash.",
-                    self.open_scheme.as_ref().unwrap()
+                    "To run this automatically, {}.",
+                    self.shell.startup_hint(self.open_scheme.as_ref().unwrap())
                 )));
 
                 Paragraph::new(lines).wrap(Wrap { trim: true })
@@ -586,8 +763,33 @@ impl App {
 
         frame.render_widget(content.clone().block(content_block.clone()), area);
 
-        if self.open_scheme.is_some() {
+        if self.open_scheme.is_some() && matches!(self.dialog_state, DialogState::Closed) {
             frame.render_widget(content_desc.clone().block(content_block), area);
+
+            if !clashes.is_empty() {
+                let height = (clashes.len() as u16 + 2).min(content_inner.height / 2);
+                let panel_area = Rect {
+                    x: content_inner.x + content_inner.width / 2,
+                    y: content_inner.bottom() - height,
+                    width: content_inner.width - content_inner.width / 2,
+                    height,
+                };
+                let panel = Paragraph::new(
+                    clashes
+                        .iter()
+                        .map(|x| Line::raw(format!("{} and {}", x.first_name, x.second_name)))
+                        .collect::<Vec<_>>(),
+                )
+                .right_aligned()
+                .block(
+                    Block::bordered()
+                        .border_type(BorderType::Rounded)
+                        .title(Line::from(" Look alike ".yellow().bold()).right_aligned()),
+                );
+
+                frame.render_widget(Clear, panel_area);
+                frame.render_widget(panel, panel_area);
+            }
         }
 
         if let DialogState::Closed = self.dialog_state {
@@ -607,25 +809,329 @@ impl App {
     }
 }
 
+/// Lists every entry of a scheme in the order they're shown
+pub fn scheme_entries(scheme: &LsColours, expanded_groups: &[String]) -> Vec<ListColourEntry> {
+    let mut entries = vec![
+        ListColourEntry {
+            entry: scheme.norm,
+            text: "Normal".to_string(),
+            description: "Global default, though everything should be set so it should be rarely used".to_string(),
+            kind: EntryKind::Builtin("no"),
+        },
+        ListColourEntry {
+            entry: scheme.file,
+            text: "File".to_string(),
+            description: "Normal file".to_string(),
+            kind: EntryKind::Builtin("fi"),
+        },
+        ListColourEntry {
+            entry: scheme.dir,
+            text: "Directory".to_string(),
+            description: "Normal directory".to_string(),
+            kind: EntryKind::Builtin("di"),
+        },
+        ListColourEntry {
+            entry: scheme.link,
+            text: "Symlink".to_string(),
+            description: match scheme.link_target {
+                true => "A symbolic link, coloured like the file it points to until it is edited",
+                false => "A symbolic link to another file on the filesystem",
+            }
+            .to_string(),
+            kind: EntryKind::Builtin("ln"),
+        },
+        ListColourEntry {
+            entry: scheme.pipe,
+            text: "Pipe".to_string(),
+            description: "A named pipe".to_string(),
+            kind: EntryKind::Builtin("pi"),
+        },
+        ListColourEntry {
+            entry: scheme.door,
+            text: "Door".to_string(),
+            description: "A door file".to_string(),
+            kind: EntryKind::Builtin("do"),
+        },
+        ListColourEntry {
+            entry: scheme.block,
+            text: "Block".to_string(),
+            description: "A block device file".to_string(),
+            kind: EntryKind::Builtin("bd"),
+        },
+        ListColourEntry {
+            entry: scheme.char,
+            text: "Character".to_string(),
+            description: "A character device file".to_string(),
+            kind: EntryKind::Builtin("cd"),
+        },
+        ListColourEntry {
+            entry: scheme.orphan,
+            text: "Orphaned Symlink".to_string(),
+            description: "A symbolic link pointing to a non-existent file".to_string(),
+            kind: EntryKind::Builtin("or"),
+        },
+        ListColourEntry {
+            entry: scheme.sock,
+            text: "Socket".to_string(),
+            description: "A socket file".to_string(),
+            kind: EntryKind::Builtin("so"),
+        },
+        ListColourEntry {
+            entry: scheme.setuid,
+            text: "SetUID".to_string(),
+            description: "A file with the SetUID bit enabled".to_string(),
+            kind: EntryKind::Builtin("su"),
+        },
+        ListColourEntry {
+            entry: scheme.setgid,
+            text: "SetGID".to_string(),
+            description: "A file with the SetGID bit enabled".to_string(),
+            kind: EntryKind::Builtin("sg"),
+        },
+        ListColourEntry {
+            entry: scheme.sticky_other_writable,
+            text: "Sticky Other Writable".to_string(),
+            description: "A directory that is sticky (only the owner can delete files), but that others can write to".to_string(),
+            kind: EntryKind::Builtin("tw"),
+        },
+        ListColourEntry {
+            entry: scheme.other_writable,
+            text: "Other Writable".to_string(),
+            description: "A directory that isn't sticky, and others can write to it".to_string(),
+            kind: EntryKind::Builtin("ow"),
+        },
+        ListColourEntry {
+            entry: scheme.sticky,
+            text: "Sticky".to_string(),
+            description: "A directory that is sticky (only the owner can delete files), but that others are unable to write to".to_string(),
+            kind: EntryKind::Builtin("st"),
+        },
+        ListColourEntry {
+            entry: scheme.exec,
+            text: "Executable".to_string(),
+            description: "An executable file".to_string(),
+            kind: EntryKind::Builtin("ex"),
+        },
+        ListColourEntry {
+            entry: scheme.missing,
+            text: "Missing".to_string(),
+            description: "A non-existent file pointed to by a symbolic link".to_string(),
+            kind: EntryKind::Builtin("mi"),
+        },
+        ListColourEntry {
+            entry: scheme.rs,
+            text: "End Code".to_string(),
+            description: "Text that isn't filenames (e.g. \'/\')".to_string(),
+            kind: EntryKind::Builtin("rs"),
+        },
+        ListColourEntry {
+            entry: scheme.multi_hardlink,
+            text: "Multi-Hardlink".to_string(),
+            description: "A regular file with more than one hard link".to_string(),
+            kind: EntryKind::Builtin("mh"),
+        },
+        ListColourEntry {
+            entry: scheme.capability,
+            text: "Capability".to_string(),
+            description: "A file with capabilities set. Looking these up is slow, so it is best left without colour".to_string(),
+            kind: EntryKind::Builtin("ca"),
+        },
+    ];
+
+    let escapes = [
+        (
+            "lc",
+            &scheme.left_code,
+            "Left Escape",
+            "Written before every colour sequence. ls uses \'\\e[\' when unset",
+        ),
+        (
+            "rc",
+            &scheme.right_code,
+            "Right Escape",
+            "Written after every colour sequence. ls uses \'m\' when unset",
+        ),
+        (
+            "ec",
+            &scheme.end_code,
+            "End Escape",
+            "Written after each name in place of the left escape, end code and right escape",
+        ),
+        (
+            "cl",
+            &scheme.clear_line,
+            "Clear Line",
+            "Clears to the end of the line when a coloured name wraps. ls uses \'\\e[K\' when unset",
+        ),
+    ];
+    for (key, code, text, description) in escapes {
+        entries.push(ListColourEntry {
+            entry: ColourEntry::default(),
+            text: text.to_string(),
+            description: description.to_string(),
+            kind: EntryKind::Escape(key, code.clone()),
+        });
+    }
+
+    for group in &scheme.groups {
+        let expanded = expanded_groups.contains(&group.name);
+        entries.push(ListColourEntry {
+            entry: group.entry,
+            text: format!(
+                "{} {} ({} patterns)",
+                match expanded {
+                    true => '-',
+                    false => '+',
+                },
+                group.name,
+                group.patterns.len()
+            ),
+            description: format!(
+                "Group sharing one colour: {}",
+                group
+                    .patterns
+                    .iter()
+                    .map(|x| format!("*{}", x))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            kind: EntryKind::Group(group.name.clone()),
+        });
+
+        if expanded {
+            for pattern in &group.patterns {
+                entries.push(ListColourEntry {
+                    entry: group.entry,
+                    text: format!(
+                        "    {}",
+                        match PatternKind::of(pattern) {
+                            PatternKind::Extension => pattern.clone(),
+                            PatternKind::Glob => format!("*{}", pattern),
+                        }
+                    ),
+                    description: format!(
+                        "Colour of {}, shared with the rest of {}",
+                        pattern_description(pattern),
+                        group.name
+                    ),
+                    kind: EntryKind::GroupMember(group.name.clone(), pattern.clone()),
+                });
+            }
+        }
+    }
+
+    for x in &scheme.extensions {
+        entries.push(ListColourEntry {
+            entry: x.1,
+            text: match PatternKind::of(&x.0) {
+                PatternKind::Extension => x.0.clone(),
+                PatternKind::Glob => format!("*{}", x.0),
+            },
+            description: format!("Colour of {}", pattern_description(&x.0)),
+            kind: EntryKind::Extension(x.0.clone()),
+        });
+    }
+
+    // The eza section comes last and only once it is turned on, since most people never use it
+    if let Some(eza) = scheme.eza_section() {
+        for (key, name, description) in eza::KEYS {
+            if let Some(x) = eza.iter().find(|x| x.0 == key) {
+                entries.push(ListColourEntry {
+                    entry: x.1,
+                    text: format!("eza: {}", name),
+                    description: description.to_string(),
+                    kind: EntryKind::Eza(key),
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+/// Whether a row of a scheme's list comes from its parent rather than the scheme itself
+fn inherited(scheme: &LsColours, entry: &ListColourEntry) -> bool {
+    if scheme.parent.is_none() {
+        return false;
+    }
+
+    match &entry.kind {
+        EntryKind::Builtin(key) => !scheme.overrides.iter().any(|x| x == key),
+        EntryKind::Escape(..) => false,
+        EntryKind::Extension(pattern) => !scheme.extensions.iter().any(|x| &x.0 == pattern),
+        EntryKind::Group(name) | EntryKind::GroupMember(name, _) => !scheme
+            .groups
+            .iter()
+            .any(|x| &x.name == name && !x.inherit_entry),
+        EntryKind::Eza(key) => !scheme.eza.iter().flatten().any(|x| &x.0 == key),
+    }
+}
+
+/// Contrast ratio of a row's colours, or `None` for rows that aren't coloured
+fn entry_contrast(scheme: &LsColours, entry: &ListColourEntry) -> Option<f64> {
+    match entry.kind {
+        EntryKind::Escape(..) => None,
+        _ => Some(scheme.contrast(&entry.entry)),
+    }
+}
+
+fn contrast_to_line(contrast: f64, scheme: &LsColours) -> Line<'static> {
+    let text = format!(
+        "Contrast {:.2}:1 on a {} terminal",
+        contrast,
+        scheme.rgb(scheme.background).to_hex()
+    );
+    match contrast < MIN_CONTRAST {
+        true => Line::raw(format!("{}, below {}:1", text, MIN_CONTRAST)).red(),
+        false => Line::raw(text),
+    }
+}
+
+/// Name of a colour, or of the slot it refers to, with the hex value it has in the scheme's palette
+fn colour_label(scheme: &LsColours, colour: Option<TerminalColour>) -> String {
+    match colour {
+        Some(c @ TerminalColour::Rgb(..)) => c.to_string(),
+        Some(c @ TerminalColour::Slot(n)) => match scheme.slots.get(n as usize) {
+            Some((name, _)) => format!("{} ({})", name, scheme.rgb(c).to_hex()),
+            None => "Missing slot".to_string(),
+        },
+        Some(c) => format!("{} ({})", c, scheme.rgb(c).to_hex()),
+        None => "None".to_string(),
+    }
+}
+
+/// Describes the files a stored pattern matches
+fn pattern_description(pattern: &str) -> String {
+    match PatternKind::of(pattern) {
+        PatternKind::Extension => format!("{} files, such as \'example{}\'", pattern, pattern),
+        PatternKind::Glob => format!("files whose names end with \'{}\'", pattern),
+    }
+}
+
 fn colour_entry_to_line(
     entry: ListColourEntry,
+    colours: ColourEntry,
+    contrast: Option<f64>,
     selected: i32,
     idx: i32,
     active: &mut Option<ListColourEntry>,
 ) -> Line<'static> {
-    let colours = entry.entry;
-
     if idx == selected {
         *active = Some(entry.clone());
     }
 
-    Line::raw(format!(
-        "[{}] {}",
+    let text = Span::raw(format!(
+        "[{}] {}{}",
         match idx == selected {
             true => '*',
             false => ' ',
         },
-        entry.text
+        entry.text,
+        match &entry.kind {
+            EntryKind::Escape(_, Some(code)) => format!(": {}", code),
+            EntryKind::Escape(_, None) => ": Unset".to_string(),
+            _ => String::new(),
+        }
     ))
     .fg(match colours.fg {
         Some(c) => c.into(),
@@ -635,11 +1141,10 @@ fn colour_entry_to_line(
         Some(c) => c.into(),
         None => Color::Reset,
     })
-    .add_modifier(match colours.effects {
-        crate::colours::Effects::Default => Modifier::empty(),
-        crate::colours::Effects::Bold => Modifier::BOLD,
-        crate::colours::Effects::Underline => Modifier::UNDERLINED,
-        crate::colours::Effects::Italic => Modifier::ITALIC,
-        crate::colours::Effects::Strikethrough => Modifier::CROSSED_OUT,
-    })
+    .add_modifier(colours.effects.modifier());
+
+    match contrast {
+        Some(x) if x < MIN_CONTRAST => Line::from(vec![text, "  Low contrast".red()]),
+        _ => Line::from(text),
+    }
 }
@@ -0,0 +1,164 @@
+use crate::{
+    colours::{ColourEntry, Hsl, LsColours, TerminalColour},
+    file::SaveFile,
+};
+
+/// Whether a scheme is meant for a dark terminal background
+pub fn is_dark(colours: &LsColours) -> bool {
+    let background = colours.rgb(colours.background);
+    background.contrast(&TerminalColour::Rgb(255, 255, 255))
+        > background.contrast(&TerminalColour::Rgb(0, 0, 0))
+}
+
+/// Remaps a scheme for the opposite terminal background. Text colours keep their hue and
+/// saturation, have their lightness flipped, and are then adjusted until they have at least the
+/// contrast they had before. The result is truecolor, since the source's named colours are only
+/// known to look right on the source's terminal
+pub fn derive(colours: &LsColours) -> LsColours {
+    let colours = colours.with_slots_resolved();
+    let background = colours.rgb(colours.background);
+    let flipped = flip(background);
+
+    let mut variant = colours.clone();
+    for entry in variant.entries_mut() {
+        *entry = remap(&colours, *entry, background, flipped);
+    }
+    variant.background = flipped;
+    variant.palette = None;
+    variant.slots = vec![];
+    variant
+}
+
+/// A scheme that is the variant of `source`, taking all its colours from it until it is edited
+pub fn linked(source: &str) -> LsColours {
+    LsColours {
+        parent: Some(source.to_string()),
+        variant: true,
+        ..LsColours::default()
+    }
+}
+
+/// Name for a new variant of a scheme, such as `nord-light`, numbered if it is taken
+pub fn name(savefile: &SaveFile, source: &str, colours: &LsColours) -> String {
+    let name = match is_dark(colours) {
+        true => format!("{}-light", source),
+        false => format!("{}-dark", source),
+    };
+
+    (1..)
+        .map(|n| match n {
+            1 => name.clone(),
+            n => format!("{}-{}", name, n),
+        })
+        .find(|x| !savefile.schemes.iter().any(|y| &y.0 == x))
+        .unwrap()
+}
+
+/// Remaps an entry's colours. Text on a background of its own reads the same on any terminal,
+/// so it is left as it is
+fn remap(
+    colours: &LsColours,
+    entry: ColourEntry,
+    background: TerminalColour,
+    flipped: TerminalColour,
+) -> ColourEntry {
+    match entry.bg {
+        Some(_) => colours.preview(entry),
+        None => ColourEntry {
+            fg: entry
+                .fg
+                .map(|fg| keep_contrast(colours.rgb(fg), background, flipped)),
+            ..entry
+        },
+    }
+}
+
+fn flip(colour: TerminalColour) -> TerminalColour {
+    let hsl = Hsl::from_rgb(colour.to_rgb());
+    Hsl {
+        l: 1.0 - hsl.l,
+        ..hsl
+    }
+    .to_colour()
+}
+
+/// Flips a colour, then moves it away from its new background until it is as readable as it was
+/// on the old one
+fn keep_contrast(
+    colour: TerminalColour,
+    background: TerminalColour,
+    flipped: TerminalColour,
+) -> TerminalColour {
+    // Text that was lighter than the old background is darker than the new one, so it gets darker
+    // still
+    let lighten = colour.luminance() < background.luminance();
+    Hsl::from_rgb(flip(colour).to_rgb()).readable(flipped, colour.contrast(&background), lighten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colours::{Effects, MIN_CONTRAST};
+
+    /// Contrast of every entry against the scheme's background
+    fn contrasts(mut colours: LsColours) -> Vec<f64> {
+        let entries = colours.entries_mut().map(|x| *x).collect::<Vec<_>>();
+        entries.iter().map(|x| colours.contrast(x)).collect()
+    }
+
+    #[test]
+    fn light_variant_stays_readable() {
+        let dark = LsColours::default();
+        assert!(is_dark(&dark));
+        let light = derive(&dark);
+        assert!(!is_dark(&light));
+
+        let before = contrasts(dark);
+        let after = contrasts(light);
+        for (i, (before, after)) in before.into_iter().zip(after).enumerate() {
+            assert!(
+                after >= before.min(MIN_CONTRAST) - 0.01,
+                "entry {} went from {:.2}:1 to {:.2}:1",
+                i,
+                before,
+                after
+            );
+        }
+    }
+
+    #[test]
+    fn linked_variant_follows_its_source() {
+        let mut savefile = SaveFile {
+            most_recent: String::new(),
+            schemes: vec![("dark".to_string(), LsColours::default())],
+        };
+        // The variant is derived from its source, keeping the link to it
+        let derived = |source: &LsColours| LsColours {
+            parent: Some("dark".to_string()),
+            variant: true,
+            ..derive(source)
+        };
+        let light = name(&savefile, "dark", &savefile.schemes[0].1);
+        assert_eq!(light, "dark-light");
+        savefile.schemes.push((light.clone(), linked("dark")));
+        assert_eq!(
+            savefile.resolve(&light),
+            Ok(derived(&savefile.schemes[0].1))
+        );
+
+        savefile.schemes[0].1.dir =
+            ColourEntry::new(Effects::NONE, Some(TerminalColour::Red), None);
+        let variant = savefile.resolve(&light).unwrap();
+        assert_eq!(variant, derived(&savefile.schemes[0].1));
+        assert_eq!(
+            variant.dir.fg.map(|x| Hsl::from_rgb(x.to_rgb()).h.round()),
+            Some(Hsl::from_rgb(TerminalColour::Red.to_rgb()).h.round())
+        );
+
+        // The next variant of the same scheme gets a number
+        assert_eq!(
+            name(&savefile, "dark", &savefile.schemes[0].1),
+            "dark-light-2"
+        );
+    }
+}
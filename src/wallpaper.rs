@@ -0,0 +1,230 @@
+use std::path::Path;
+
+use crate::{
+    colours::{Hsl, LsColours, TerminalColour, MIN_CONTRAST},
+    lint,
+    presets::{self, Palette},
+};
+
+/// Number of colours an image is reduced to before roles are picked from them
+const CLUSTERS: usize = 16;
+
+/// Contrast the background keeps against white or black text, so every role can reach
+/// `MIN_CONTRAST` by moving towards it
+const BACKGROUND_CONTRAST: f64 = 12.0;
+
+/// Contrast of the foreground against the background, matching WCAG AAA
+const FOREGROUND_CONTRAST: f64 = 7.0;
+
+/// How far, in degrees, an image colour's hue can be from a role's hue and still be used for it
+const HUE_TOLERANCE: f64 = 35.0;
+
+/// Builds a truecolor scheme from the dominant colours of a PNG or JPEG image. Every role keeps
+/// at least `MIN_CONTRAST` against the background, and hues the image lacks are made up at the
+/// image's average saturation
+pub fn scheme(path: &Path) -> Result<LsColours, String> {
+    let image = image::open(path)
+        .map_err(|e| e.to_string())?
+        .thumbnail(128, 128)
+        .to_rgb8();
+    let pixels = image.pixels().map(|x| x.0).collect::<Vec<_>>();
+    if pixels.is_empty() {
+        return Err("Image is empty".to_string());
+    }
+
+    Ok(presets::themed(&roles(&median_cut(pixels, CLUSTERS))))
+}
+
+/// Reduces pixels to at most `count` colours by repeatedly splitting the box with the widest
+/// channel at its median. Returns each colour with the number of pixels it stands for
+fn median_cut(pixels: Vec<[u8; 3]>, count: usize) -> Vec<((u8, u8, u8), usize)> {
+    let range = |pixels: &[[u8; 3]], channel: usize| {
+        let values = pixels.iter().map(|x| x[channel]);
+        values.clone().max().unwrap_or(0) - values.min().unwrap_or(0)
+    };
+    let widest = |pixels: &[[u8; 3]]| (0..3).max_by_key(|x| range(pixels, *x)).unwrap();
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        let (i, channel) = match boxes
+            .iter()
+            .enumerate()
+            .filter(|x| x.1.len() > 1)
+            .max_by_key(|x| range(x.1, widest(x.1)) as usize * x.1.len())
+        {
+            Some((i, pixels)) => (i, widest(pixels)),
+            None => break,
+        };
+
+        let mut pixels = boxes.swap_remove(i);
+        pixels.sort_by_key(|x| x[channel]);
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    boxes
+        .into_iter()
+        .map(|pixels| {
+            let sum = |channel: usize| pixels.iter().map(|x| x[channel] as usize).sum::<usize>();
+            let n = pixels.len();
+            (
+                ((sum(0) / n) as u8, (sum(1) / n) as u8, (sum(2) / n) as u8),
+                n,
+            )
+        })
+        .collect()
+}
+
+/// Picks the colour of each role, keeping the image's overall lightness for the background
+fn roles(clusters: &[((u8, u8, u8), usize)]) -> Palette {
+    let colours = clusters
+        .iter()
+        .map(|x| (Hsl::from_rgb(x.0), x.1))
+        .collect::<Vec<_>>();
+    let dominant = colours.iter().max_by_key(|x| x.1).unwrap().0;
+    let dark = dominant.l < 0.5;
+    let text = match dark {
+        true => TerminalColour::White,
+        false => TerminalColour::Black,
+    };
+
+    let background = Hsl {
+        s: dominant.s.min(0.3),
+        ..dominant
+    }
+    .readable(text, BACKGROUND_CONTRAST, !dark);
+
+    let chromatic = colours
+        .iter()
+        .filter(|x| x.0.s > 0.25 && (0.15..0.9).contains(&x.0.l))
+        .collect::<Vec<_>>();
+    let saturation = match chromatic.is_empty() {
+        true => 0.5,
+        false => chromatic.iter().map(|x| x.0.s).sum::<f64>() / chromatic.len() as f64,
+    }
+    .clamp(0.4, 0.8);
+
+    // Each role takes the most common image colour near its hue that stays apart from the roles
+    // already picked, and makes one up if there is none
+    let mut picked: Vec<TerminalColour> = vec![];
+    let mut role = |hue: f64| {
+        let distance = |h: f64| (h - hue).rem_euclid(360.0).min((hue - h).rem_euclid(360.0));
+        let mut candidates = chromatic
+            .iter()
+            .filter(|x| distance(x.0.h) < HUE_TOLERANCE)
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|x| std::cmp::Reverse(x.1));
+
+        let made_up = Hsl {
+            h: hue,
+            s: saturation,
+            l: match dark {
+                true => 0.6,
+                false => 0.4,
+            },
+        };
+        let colour = candidates
+            .iter()
+            .map(|x| x.0.readable(background, MIN_CONTRAST, dark))
+            .find(|x| picked.iter().all(|y| x.distance(y) >= lint::MIN_DISTANCE))
+            .unwrap_or_else(|| made_up.readable(background, MIN_CONTRAST, dark));
+        picked.push(colour);
+        colour
+    };
+
+    let red = role(0.0);
+    let yellow = role(50.0);
+    let green = role(120.0);
+    let cyan = role(185.0);
+    let blue = role(220.0);
+    let magenta = role(300.0);
+
+    // Text is the image colour furthest from the background, with most of its colour taken out
+    let extreme = colours
+        .iter()
+        .map(|x| x.0)
+        .max_by(|a, b| match dark {
+            true => a.l.total_cmp(&b.l),
+            false => b.l.total_cmp(&a.l),
+        })
+        .unwrap();
+    let foreground = Hsl {
+        s: extreme.s.min(0.15),
+        ..extreme
+    }
+    .readable(background, FOREGROUND_CONTRAST, dark);
+    let muted = Hsl {
+        s: extreme.s.min(0.15),
+        l: Hsl::from_rgb(background.to_rgb()).l,
+        ..extreme
+    }
+    .readable(background, MIN_CONTRAST, dark);
+
+    Palette {
+        foreground,
+        background,
+        muted,
+        red,
+        green,
+        yellow,
+        blue,
+        magenta,
+        cyan,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scheme from a generated image made of stripes of the given colours
+    fn scheme_from(name: &str, stripes: &[[u8; 3]]) -> LsColours {
+        let image = image::RgbImage::from_fn(64, 64, |x, _| {
+            image::Rgb(stripes[x as usize * stripes.len() / 64])
+        });
+        let path = std::env::temp_dir().join(format!("lscoltui-{}.png", name));
+        image.save(&path).unwrap();
+        let colours = scheme(&path);
+        std::fs::remove_file(&path).unwrap();
+        colours.unwrap()
+    }
+
+    #[test]
+    fn every_entry_is_readable() {
+        let dark = scheme_from(
+            "dark",
+            &[
+                [20, 24, 40],
+                [20, 24, 40],
+                [30, 60, 140],
+                [40, 120, 60],
+                [150, 40, 40],
+            ],
+        );
+        let light = scheme_from(
+            "light",
+            &[
+                [240, 236, 220],
+                [240, 236, 220],
+                [90, 140, 220],
+                [120, 200, 120],
+                [230, 180, 60],
+            ],
+        );
+
+        for colours in [dark, light] {
+            let mut colours = colours.with_slots_resolved();
+            let entries = colours.entries_mut().map(|x| *x).collect::<Vec<_>>();
+            for entry in entries {
+                let contrast = colours.contrast(&entry);
+                assert!(
+                    contrast >= MIN_CONTRAST,
+                    "{} has a contrast of {:.2}",
+                    entry.parse(),
+                    contrast
+                );
+            }
+        }
+    }
+}